@@ -0,0 +1,1169 @@
+//! Options shared by the serializer and the deserializer.
+
+use std::borrow::Cow;
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+use crate::error::Error;
+
+/// How the elements of a sequence are laid out in the query string.
+///
+/// The examples show how `filter: vec!["a", "b"]` is written with each style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SequenceStyle {
+    /// `filter=a,b`
+    #[default]
+    Comma,
+    /// `filter=a&filter=b`
+    Repeated,
+    /// `filter[]=a&filter[]=b`
+    Brackets,
+    /// `filter[0]=a&filter[1]=b`
+    Indexed,
+    /// `filter=a%20b`, the OpenAPI `spaceDelimited` style. The spaces inside
+    /// the elements are written as `+`, whatever the [`Encoding`].
+    SpaceDelimited,
+    /// `filter=a|b`, the OpenAPI `pipeDelimited` style.
+    PipeDelimited,
+    /// The elements are joined with the given delimiter, written as-is.
+    /// `Delimited(";")` writes `filter=a;b`.
+    ///
+    /// The delimiter must be made of ASCII punctuation other than `%`, so it
+    /// can be escaped inside the elements, and can't contain `&`, `=`, `#`
+    /// or the separators of the parameters.
+    Delimited(&'static str),
+}
+
+impl SequenceStyle {
+    /// A delimiter written as `%20`, the elements write their spaces as `+`
+    /// whatever the [`Encoding`].
+    const SPACE: &'static str = "%20";
+
+    /// The delimiter written between the elements when they share a single
+    /// parameter, `None` when every element gets its own parameter.
+    pub(crate) fn delimiter(self) -> Option<&'static str> {
+        match self {
+            SequenceStyle::Comma => Some(","),
+            SequenceStyle::SpaceDelimited => Some(Self::SPACE),
+            SequenceStyle::PipeDelimited => Some("|"),
+            SequenceStyle::Delimited(delimiter) => Some(delimiter),
+            SequenceStyle::Repeated | SequenceStyle::Brackets | SequenceStyle::Indexed => None,
+        }
+    }
+}
+
+/// Escape what would be mistaken for the `delimiter` in an encoded element
+/// of a sequence.
+///
+/// No character of the delimiter is left as-is in the element, so splitting
+/// on the delimiter gives back the elements.
+fn escape_element<'a>(
+    element: &'a str,
+    delimiter: &'static str,
+    encoding: Encoding,
+) -> Result<Cow<'a, str>, Error> {
+    if delimiter == SequenceStyle::SPACE {
+        // a space is written as `%20` by the percent-encoding, and as `+` by the form-encoding
+        if encoding == Encoding::Form || !element.contains(['+', '%']) {
+            return Ok(Cow::Borrowed(element));
+        }
+        return Ok(Cow::Owned(element.replace('+', "%2B").replace("%20", "+")));
+    }
+    if !element.contains(|c| delimiter.contains(c)) {
+        return Ok(Cow::Borrowed(element));
+    }
+    let mut escaped = String::with_capacity(element.len());
+    for c in element.chars() {
+        match c {
+            // a form-encoded `+` is a space
+            '+' if encoding == Encoding::Form && delimiter.contains(c) => escaped.push_str("%20"),
+            c if delimiter.contains(c) => {
+                escaped.push_str(percent_encoding::percent_encode_byte(c as u8))
+            }
+            c => escaped.push(c),
+        }
+    }
+    Ok(Cow::Owned(escaped))
+}
+
+/// How the structs and maps nested in a value are laid out in the query
+/// string.
+///
+/// Nested values are only supported when serializing. The examples show how
+/// `filter: Filter { name: "kefir", age: Range { gte: 3 } }` is written with
+/// each style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NestingStyle {
+    /// Nested values are rejected with
+    /// [`Error::UnsupportedNestedStruct`](crate::Error::UnsupportedNestedStruct).
+    #[default]
+    Unsupported,
+    /// `filter[name]=kefir&filter[age][gte]=3`, to any depth.
+    Brackets,
+    /// `filter[name]=kefir`, the OpenAPI `deepObject` style. Only a single
+    /// level of nesting is supported, the `age` field is rejected.
+    DeepObject,
+    /// `filter.name=kefir&filter.age.gte=3`, to any depth. The dots inside
+    /// the keys are percent-encoded as `%2E`.
+    Dotted,
+}
+
+impl NestingStyle {
+    /// Whether a struct or a map can be written in place of a value nested
+    /// `depth` levels deep.
+    pub(crate) fn allows(self, depth: usize) -> bool {
+        match self {
+            NestingStyle::Unsupported => false,
+            NestingStyle::Brackets | NestingStyle::Dotted => true,
+            NestingStyle::DeepObject => depth == 0,
+        }
+    }
+
+    /// Escape what would be mistaken for a nesting separator in a key.
+    pub(crate) fn escape(self, key: &str) -> Cow<'_, str> {
+        match self {
+            NestingStyle::Dotted if key.contains('.') => Cow::Owned(key.replace('.', "%2E")),
+            _ => Cow::Borrowed(key),
+        }
+    }
+
+    /// The key of the `child` field of the value written under `parent`.
+    pub(crate) fn nest(self, parent: &str, child: &str) -> String {
+        match self {
+            NestingStyle::Dotted => format!("{parent}.{}", self.escape(child)),
+            _ => format!("{parent}[{child}]"),
+        }
+    }
+}
+
+/// How a sequence of structs or maps is written in place of a value.
+///
+/// The examples show how `items: vec![Item { id: 1, qty: 3 }, Item { id: 2,
+/// qty: 4 }]` is written with each style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StructSequenceStyle {
+    /// Sequences of structs are rejected with
+    /// [`Error::UnsupportedNestedStruct`](crate::Error::UnsupportedNestedStruct).
+    #[default]
+    Unsupported,
+    /// `items[0][id]=1&items[0][qty]=3&items[1][id]=2&items[1][qty]=4`, a
+    /// parameter per field of each element. An element with every field
+    /// omitted is rejected.
+    Indexed,
+    /// `items.id=1,2&items.qty=3,4`, a sequence per field laid out with the
+    /// [`SequenceStyle`]. The elements must all have the same fields.
+    Columns,
+}
+
+/// What to write in place of a value that has nothing to show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OmitPolicy {
+    /// Leave the parameter out, `key` doesn't appear at all. An element of
+    /// a sequence is left empty instead, to keep its position.
+    #[default]
+    Omit,
+    /// Write the parameter without any value, `key=`.
+    Empty,
+    /// Write the parameter with the null token of the [`Config`], `key=null`
+    /// by default.
+    Null,
+}
+
+/// How an enum is written in place of a value.
+///
+/// The examples show how `filter: Filter::Id(3)` and `sort: Sort::Asc`, the
+/// first variant of `Sort`, are written with each style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnumStyle {
+    /// `filter=3&sort=Asc`, the variants holding a value only write their
+    /// value.
+    #[default]
+    Name,
+    /// `filter=1:3&sort=0`, the variants are written as their index, followed
+    /// by their value like with [`EnumStyle::Prefixed`].
+    Index,
+    /// `filter=Id:3&sort=Asc`.
+    ///
+    /// With the sequence styles writing a parameter per element, the variants
+    /// holding a sequence are rejected. So are the variants holding a `None`
+    /// omitted by the [`OmitPolicy`].
+    Prefixed,
+    /// `filter=3&filter_type=Id&sort=Asc` with `TagParam("_type")`, the
+    /// variant is sent in its own parameter, named after the key and the
+    /// given suffix.
+    ///
+    /// The variants holding a value are rejected inside a sequence.
+    TagParam(&'static str),
+}
+
+/// The case the variant names are converted to, the way serde's `rename_all`
+/// converts PascalCase variants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VariantCase {
+    /// `ById`
+    #[default]
+    Unchanged,
+    /// `by_id`
+    Snake,
+    /// `by-id`
+    Kebab,
+    /// `byId`
+    Camel,
+}
+
+impl VariantCase {
+    pub(crate) fn apply(self, variant: &str) -> Cow<'_, str> {
+        let separator = match self {
+            VariantCase::Unchanged => return Cow::Borrowed(variant),
+            VariantCase::Camel => {
+                let mut chars = variant.chars();
+                return match chars.next() {
+                    Some(first) => Cow::Owned(first.to_lowercase().chain(chars).collect()),
+                    None => Cow::Borrowed(variant),
+                };
+            }
+            VariantCase::Snake => '_',
+            VariantCase::Kebab => '-',
+        };
+        let mut converted = String::with_capacity(variant.len() + 4);
+        for (i, c) in variant.char_indices() {
+            if i > 0 && c.is_uppercase() {
+                converted.push(separator);
+            }
+            converted.extend(c.to_lowercase());
+        }
+        Cow::Owned(converted)
+    }
+}
+
+/// How a unit struct is written in place of a value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnitStructStyle {
+    /// Like a `()`, following the unit policy of the [`Config`].
+    #[default]
+    Unit,
+    /// The name of the struct, `struct Latest;` writes `key=Latest`.
+    Name,
+}
+
+/// How the finite floats are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// The shortest representation reading back as the same float, `0.1`.
+    #[default]
+    Shortest,
+    /// Like [`FloatFormat::Shortest`], with an exponent for the large and
+    /// tiny floats, `0.1`, `2`, `1e300` or `2.5e-7`.
+    Exponent,
+    /// A fixed number of digits after the decimal point, `0.100` with
+    /// `Precision(3)`.
+    Precision(usize),
+}
+
+/// How the NaN and the infinite floats are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFiniteFloats {
+    /// `NaN`, `inf` and `-inf`.
+    #[default]
+    Display,
+    /// Return an error.
+    Error,
+    /// The given tokens.
+    Tokens {
+        /// The token of NaN.
+        nan: &'static str,
+        /// The token of the positive infinity.
+        inf: &'static str,
+        /// The token of the negative infinity.
+        neg_inf: &'static str,
+    },
+}
+
+/// How a bool is written in place of a value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoolStyle {
+    /// `true` and `false`.
+    #[default]
+    TrueFalse,
+    /// `1` and `0`.
+    OneZero,
+    /// `yes` and `no`.
+    YesNo,
+    /// `on`, and the parameter is left out for `false`, like an HTML
+    /// checkbox.
+    Checkbox,
+}
+
+impl BoolStyle {
+    /// The token of the bool, `None` when it is left out.
+    pub(crate) fn token(self, v: bool) -> Option<&'static str> {
+        let (yes, no) = match self {
+            BoolStyle::TrueFalse => ("true", "false"),
+            BoolStyle::OneZero => ("1", "0"),
+            BoolStyle::YesNo => ("yes", "no"),
+            BoolStyle::Checkbox => return v.then_some("on"),
+        };
+        Some(if v { yes } else { no })
+    }
+
+    pub(crate) fn parse(self, token: &str) -> Option<bool> {
+        match self {
+            // an unchecked checkbox may still be sent empty
+            BoolStyle::Checkbox if token.is_empty() => Some(false),
+            _ if self.token(true) == Some(token) => Some(true),
+            _ if self.token(false) == Some(token) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// How the keys and values are percent-encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// `application/x-www-form-urlencoded`, spaces are written as `+`.
+    #[default]
+    Form,
+    /// RFC 3986, everything but the unreserved characters is percent-encoded
+    /// and spaces are written as `%20`.
+    Rfc3986,
+    /// RFC 3986, except that the given characters are written as-is too.
+    ///
+    /// `Custom(",:/")` keeps a filter like `genres:comedy,drama` readable. The
+    /// safe characters must be ASCII punctuation other than `%` and the
+    /// characters ending a parameter: `&`, `=`, `#` and the separators of the
+    /// [`Config`].
+    Custom(&'static str),
+}
+
+/// Everything but the RFC 3986 unreserved characters.
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+impl Encoding {
+    pub(crate) fn encode(self, input: &str) -> String {
+        match self {
+            Encoding::Form => String::from_iter(form_urlencoded::byte_serialize(input.as_bytes())),
+            Encoding::Rfc3986 => percent_encoding::utf8_percent_encode(input, RFC3986).to_string(),
+            Encoding::Custom(safe) => {
+                let mut encoded = String::with_capacity(input.len());
+                for c in input.chars() {
+                    if c.is_ascii_alphanumeric()
+                        || "-._~".contains(c)
+                        || c.is_ascii() && safe.contains(c)
+                    {
+                        encoded.push(c);
+                    } else {
+                        for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                            encoded.push_str(percent_encoding::percent_encode_byte(byte));
+                        }
+                    }
+                }
+                encoded
+            }
+        }
+    }
+
+    /// Whether the character is written as-is.
+    fn keeps(self, c: char) -> bool {
+        let kept = match self {
+            Encoding::Form => "*-._",
+            Encoding::Rfc3986 => "-._~",
+            Encoding::Custom(safe) => {
+                return c.is_ascii_alphanumeric()
+                    || "-._~".contains(c)
+                    || c.is_ascii() && safe.contains(c);
+            }
+        };
+        c.is_ascii_alphanumeric() || kept.contains(c)
+    }
+
+    pub(crate) fn decode(self, raw: &str) -> Result<Cow<'_, str>, std::str::Utf8Error> {
+        if self != Encoding::Form || !raw.contains('+') {
+            return percent_encoding::percent_decode_str(raw).decode_utf8();
+        }
+        let raw = raw.replace('+', " ");
+        let decoded = percent_encoding::percent_decode_str(&raw).decode_utf8()?;
+        Ok(Cow::Owned(decoded.into_owned()))
+    }
+
+    pub(crate) fn encode_bytes(self, input: &[u8]) -> String {
+        match self {
+            Encoding::Form => String::from_iter(form_urlencoded::byte_serialize(input)),
+            Encoding::Rfc3986 => percent_encoding::percent_encode(input, RFC3986).to_string(),
+            Encoding::Custom(safe) => {
+                let mut encoded = String::with_capacity(input.len());
+                for &byte in input {
+                    let c = char::from(byte);
+                    if byte.is_ascii_alphanumeric()
+                        || b"-._~".contains(&byte)
+                        || byte.is_ascii() && safe.contains(c)
+                    {
+                        encoded.push(c);
+                    } else {
+                        encoded.push_str(percent_encoding::percent_encode_byte(byte));
+                    }
+                }
+                encoded
+            }
+        }
+    }
+
+    pub(crate) fn decode_bytes(self, raw: &str) -> Vec<u8> {
+        if self == Encoding::Form {
+            let raw = raw.replace('+', " ");
+            return percent_encoding::percent_decode_str(&raw).collect();
+        }
+        percent_encoding::percent_decode_str(raw).collect()
+    }
+}
+
+/// How bytes, like a `serde_bytes::ByteBuf`, are written in place of a value.
+///
+/// The examples show how `b"hi"` is written with each encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// `104,105`, the sequence of the numbers of the bytes.
+    #[default]
+    Numbers,
+    /// `aGk%3D`, base64url with its padding, percent-encoded.
+    Base64Url,
+    /// `aGk`, base64url without padding.
+    Base64UrlUnpadded,
+    /// `6869`, lowercase hexadecimal.
+    Hex,
+    /// `hi`, percent-encoded byte by byte like a string, a `0xff` byte is
+    /// written as `%FF`.
+    Percent,
+}
+
+impl BytesEncoding {
+    /// The bytes written as a single value, or `None` when they are written
+    /// as a sequence of numbers.
+    pub(crate) fn encode(self, bytes: &[u8], encoding: Encoding) -> Option<String> {
+        use base64::Engine;
+
+        let base64 = match self {
+            BytesEncoding::Numbers => return None,
+            BytesEncoding::Base64Url => base64::engine::general_purpose::URL_SAFE.encode(bytes),
+            BytesEncoding::Base64UrlUnpadded => {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+            }
+            BytesEncoding::Hex => {
+                return Some(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+            }
+            BytesEncoding::Percent => return Some(encoding.encode_bytes(bytes)),
+        };
+        // the padding is percent-encoded like any `=`
+        Some(encoding.encode(&base64))
+    }
+
+    /// Read bytes written as a single value, `None` when they can't be
+    /// decoded.
+    pub(crate) fn decode(self, raw: &str, encoding: Encoding) -> Option<Vec<u8>> {
+        use base64::Engine;
+
+        match self {
+            BytesEncoding::Numbers => None,
+            BytesEncoding::Base64Url => base64::engine::general_purpose::URL_SAFE
+                .decode(encoding.decode(raw).ok()?.as_bytes())
+                .ok(),
+            BytesEncoding::Base64UrlUnpadded => base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(encoding.decode(raw).ok()?.as_bytes())
+                .ok(),
+            BytesEncoding::Hex => {
+                let raw = encoding.decode(raw).ok()?;
+                if raw.len() % 2 != 0 {
+                    return None;
+                }
+                (0..raw.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(raw.get(i..i + 2)?, 16).ok())
+                    .collect()
+            }
+            BytesEncoding::Percent => Some(encoding.decode_bytes(raw)),
+        }
+    }
+}
+
+/// A preset reproducing the output of another crate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compat {
+    /// The nyaup format, where the values with nothing to show are omitted.
+    #[default]
+    Nyaup,
+    /// The format of [yaup](https://github.com/meilisearch/yaup) 0.3, where
+    /// nothing is omitted: a `None` is written as `key=null`, and the empty
+    /// strings, the empty sequences and `()` as `key=`.
+    Yaup,
+}
+
+/// Options used to serialize into and deserialize from URL parameters.
+///
+/// The same configuration should be used on both sides for a value to
+/// round-trip.
+///
+/// ```
+/// use nyaup::{Config, SequenceStyle};
+///
+/// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct Params {
+///     filter: Vec<String>,
+/// }
+///
+/// let config = Config::new().sequence_style(SequenceStyle::Repeated);
+/// let params = Params { filter: vec![String::from("kefir"), String::from("echo")] };
+///
+/// let query = nyaup::to_string_with(&params, &config).unwrap();
+/// assert_eq!(query, "?filter=kefir&filter=echo");
+/// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub(crate) prefix: Cow<'static, str>,
+    pub(crate) pair_separator: char,
+    pub(crate) key_value_separator: char,
+    pub(crate) none_policy: OmitPolicy,
+    pub(crate) empty_str_policy: OmitPolicy,
+    pub(crate) empty_seq_policy: OmitPolicy,
+    pub(crate) unit_policy: OmitPolicy,
+    pub(crate) null_token: Cow<'static, str>,
+    pub(crate) unit_struct_style: UnitStructStyle,
+    pub(crate) bytes_encoding: BytesEncoding,
+    pub(crate) float_format: FloatFormat,
+    pub(crate) non_finite_floats: NonFiniteFloats,
+    pub(crate) bool_style: BoolStyle,
+    pub(crate) enum_style: EnumStyle,
+    pub(crate) variant_case: VariantCase,
+    pub(crate) variant_tag: Option<&'static str>,
+    pub(crate) encoding: Encoding,
+    pub(crate) sequence_style: SequenceStyle,
+    pub(crate) nested_delimiters: &'static [&'static str],
+    pub(crate) inline_pairs: Option<&'static str>,
+    pub(crate) nesting_style: NestingStyle,
+    pub(crate) struct_sequence_style: StructSequenceStyle,
+    #[cfg(feature = "json")]
+    pub(crate) json_fallback: bool,
+    pub(crate) compat: Compat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Cow::Borrowed("?"),
+            pair_separator: '&',
+            key_value_separator: '=',
+            none_policy: OmitPolicy::Omit,
+            empty_str_policy: OmitPolicy::Omit,
+            empty_seq_policy: OmitPolicy::Omit,
+            unit_policy: OmitPolicy::Omit,
+            null_token: Cow::Borrowed("null"),
+            unit_struct_style: UnitStructStyle::Unit,
+            bytes_encoding: BytesEncoding::Numbers,
+            float_format: FloatFormat::Shortest,
+            non_finite_floats: NonFiniteFloats::Display,
+            bool_style: BoolStyle::TrueFalse,
+            enum_style: EnumStyle::Name,
+            variant_case: VariantCase::Unchanged,
+            variant_tag: None,
+            encoding: Encoding::Form,
+            sequence_style: SequenceStyle::Comma,
+            nested_delimiters: &[],
+            inline_pairs: None,
+            nesting_style: NestingStyle::Unsupported,
+            struct_sequence_style: StructSequenceStyle::Unsupported,
+            #[cfg(feature = "json")]
+            json_fallback: false,
+            compat: Compat::Nyaup,
+        }
+    }
+}
+
+impl Config {
+    /// Create the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reproduce the output of another crate, overriding the omission
+    /// policies.
+    ///
+    /// ```
+    /// use nyaup::{Compat, Config};
+    ///
+    /// #[derive(serde::Serialize)]
+    /// struct Params {
+    ///     doggo: Vec<&'static str>,
+    ///     catto: Option<&'static str>,
+    /// }
+    ///
+    /// let params = Params { doggo: Vec::new(), catto: None };
+    /// let config = Config::new().compat(Compat::Yaup);
+    /// assert_eq!(nyaup::to_string_with(&params, &config).unwrap(), "?doggo=&catto=null");
+    /// ```
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.compat = compat;
+        match compat {
+            Compat::Nyaup => self
+                .none_policy(OmitPolicy::Omit)
+                .empty_policy(OmitPolicy::Omit)
+                .unit_policy(OmitPolicy::Omit),
+            Compat::Yaup => self
+                .none_policy(OmitPolicy::Null)
+                .empty_policy(OmitPolicy::Empty)
+                .unit_policy(OmitPolicy::Empty),
+        }
+    }
+
+    /// The delimiter of a sequence nested in `level` others, `None` when its
+    /// elements get their own parameters or when it can't be nested.
+    pub(crate) fn delimiter(&self, level: usize) -> Option<&'static str> {
+        match level {
+            0 => self.sequence_style.delimiter(),
+            _ => self.nested_delimiters.get(level - 1).copied(),
+        }
+    }
+
+    /// Escape what would be mistaken for the delimiter of any of the `depth`
+    /// sequences holding an encoded element.
+    pub(crate) fn escape_element<'a>(
+        &self,
+        element: &'a str,
+        depth: usize,
+    ) -> Result<Cow<'a, str>, Error> {
+        // yaup doesn't escape anything
+        if self.compat == Compat::Yaup {
+            return Ok(Cow::Borrowed(element));
+        }
+        let mut element = Cow::Borrowed(element);
+        // the outermost delimiter goes last, the spaces it writes as `+` can't be escaped again
+        for level in (0..depth).rev() {
+            let Some(delimiter) = self.delimiter(level) else {
+                continue;
+            };
+            self.check_level(level)?;
+            if let Cow::Owned(escaped) = escape_element(&element, delimiter, self.encoding)? {
+                element = Cow::Owned(escaped);
+            }
+        }
+        Ok(element)
+    }
+
+    /// Escape what would be mistaken for the separator of the inline pairs in
+    /// the key or the value of a pair, itself an element of `depth`
+    /// sequences.
+    pub(crate) fn escape_pair_part<'a>(
+        &self,
+        part: &'a str,
+        depth: usize,
+    ) -> Result<Cow<'a, str>, Error> {
+        match self.inline_pairs {
+            Some(separator) if self.compat != Compat::Yaup => {
+                self.check_inner_delimiter(separator, depth)?;
+                escape_element(part, separator, self.encoding)
+            }
+            _ => Ok(Cow::Borrowed(part)),
+        }
+    }
+
+    /// Make sure the separators can't be found as-is inside a key or a value,
+    /// and that the characters kept by the encoding can be decoded.
+    pub(crate) fn check_separators(&self) -> Result<(), Error> {
+        if let Encoding::Custom(safe) = self.encoding {
+            let reserved = self.reserved_chars();
+            if let Some(c) = safe
+                .chars()
+                .find(|&c| !c.is_ascii_punctuation() || c == '%' || reserved.contains(&c))
+            {
+                return Err(Error::UnsupportedSafeChar(c));
+            }
+        }
+        for separator in [self.pair_separator, self.key_value_separator] {
+            if self.encoding.keeps(separator)
+                || "[]%+ ".contains(separator)
+                // the variants are written as `Variant:value`
+                || separator == ':'
+                    && matches!(self.enum_style, EnumStyle::Prefixed | EnumStyle::Index)
+                || self.pair_separator == self.key_value_separator
+            {
+                return Err(Error::UnsupportedSeparator(separator));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure the delimiter of a sequence nested in `level` others can be
+    /// told apart from the rest of the query string.
+    pub(crate) fn check_level(&self, level: usize) -> Result<(), Error> {
+        match self.delimiter(level) {
+            None => Ok(()),
+            // the spaces are escaped by the encoding
+            Some(SequenceStyle::SPACE) if level == 0 => Ok(()),
+            Some(delimiter) if level == 0 => self.check_delimiter(delimiter),
+            Some(delimiter) => {
+                self.check_inner_delimiter(delimiter, level)?;
+                // the inner sequences may be the key or the value of an inline pair
+                match self.inline_pairs {
+                    Some(separator) if delimiter.contains(|c| separator.contains(c)) => {
+                        Err(Error::UnsupportedDelimiter(delimiter))
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// The characters ending a parameter or its key, never written as-is in
+    /// a value.
+    pub(crate) fn reserved_chars(&self) -> [char; 5] {
+        ['&', '=', '#', self.pair_separator, self.key_value_separator]
+    }
+
+    /// Make sure the delimiter can be escaped inside the elements and can't
+    /// be mistaken for the end of the parameter.
+    fn check_delimiter(&self, delimiter: &'static str) -> Result<(), Error> {
+        let reserved = self.reserved_chars();
+        if delimiter.is_empty()
+            || !delimiter
+                .chars()
+                .all(|c| c.is_ascii_punctuation() && c != '%' && !reserved.contains(&c))
+        {
+            return Err(Error::UnsupportedDelimiter(delimiter));
+        }
+        Ok(())
+    }
+
+    /// Make sure a delimiter can't be mistaken for the delimiter of any of
+    /// the `level` sequences holding it.
+    fn check_inner_delimiter(&self, delimiter: &'static str, level: usize) -> Result<(), Error> {
+        self.check_delimiter(delimiter)?;
+        for outer in (0..level).filter_map(|level| self.delimiter(level)) {
+            // the spaces of the space-delimited elements are written as `+`
+            let outer = if outer == SequenceStyle::SPACE {
+                "+"
+            } else {
+                outer
+            };
+            if delimiter.contains(|c| outer.contains(c)) {
+                return Err(Error::UnsupportedDelimiter(delimiter));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a float following the float options, non-finite tokens are
+    /// percent-encoded.
+    pub(crate) fn format_float<F>(&self, v: F) -> Result<String, Error>
+    where
+        F: std::fmt::Display + std::fmt::Debug + Copy + Into<f64>,
+    {
+        let float: f64 = v.into();
+        if float.is_finite() {
+            return Ok(match self.float_format {
+                FloatFormat::Shortest => v.to_string(),
+                // `Debug` switches to an exponent for the large and tiny floats, but writes `2` as `2.0`
+                FloatFormat::Exponent => {
+                    let shortest = format!("{v:?}");
+                    match shortest.strip_suffix(".0") {
+                        Some(integer) => integer.to_string(),
+                        None => shortest,
+                    }
+                }
+                FloatFormat::Precision(precision) => format!("{v:.precision$}"),
+            });
+        }
+        match self.non_finite_floats {
+            NonFiniteFloats::Display => Ok(v.to_string()),
+            NonFiniteFloats::Error => Err(Error::NonFiniteFloat(float)),
+            NonFiniteFloats::Tokens { nan, inf, neg_inf } => {
+                let token = match float {
+                    f if f.is_nan() => nan,
+                    f if f > 0.0 => inf,
+                    _ => neg_inf,
+                };
+                Ok(self.encoding.encode(token))
+            }
+        }
+    }
+
+    /// Read a float, decoded, written following the float options.
+    pub(crate) fn parse_float<F>(&self, decoded: &str) -> Option<F>
+    where
+        F: std::str::FromStr + From<f32>,
+    {
+        if let NonFiniteFloats::Tokens { nan, inf, neg_inf } = self.non_finite_floats {
+            let float = match decoded {
+                _ if decoded == nan => f32::NAN,
+                _ if decoded == inf => f32::INFINITY,
+                _ if decoded == neg_inf => f32::NEG_INFINITY,
+                _ => return decoded.parse().ok(),
+            };
+            return Some(F::from(float));
+        }
+        decoded.parse().ok()
+    }
+
+    /// Whether an element of a sequence reads a `+` as a space whatever the
+    /// encoding, see [`escape_element`].
+    pub(crate) fn plus_is_space_in_elements(&self) -> bool {
+        self.sequence_style.delimiter() == Some(SequenceStyle::SPACE) && self.compat != Compat::Yaup
+    }
+
+    /// Set what is written before the first parameter, `?` by default.
+    ///
+    /// When deserializing, the prefix is optional.
+    pub fn prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set what is written between two parameters, `&` by default.
+    ///
+    /// The separators must be characters the [`Encoding`] never writes as-is,
+    /// other than `[`, `]`, `%`, `+`, a space, or `:` with
+    /// [`EnumStyle::Prefixed`] or [`EnumStyle::Index`], and different from
+    /// each other.
+    pub fn pair_separator(mut self, separator: char) -> Self {
+        self.pair_separator = separator;
+        self
+    }
+
+    /// Set what is written between a key and its value, `=` by default.
+    ///
+    /// See [`Config::pair_separator`] for the supported separators.
+    pub fn key_value_separator(mut self, separator: char) -> Self {
+        self.key_value_separator = separator;
+        self
+    }
+
+    /// Set what is written for a `None`, [`OmitPolicy::Omit`] by default.
+    pub fn none_policy(mut self, policy: OmitPolicy) -> Self {
+        self.none_policy = policy;
+        self
+    }
+
+    /// Set what is written for an empty string, [`OmitPolicy::Omit`] by
+    /// default.
+    ///
+    /// With [`OmitPolicy::Null`], a `null` value is read back as an empty
+    /// string.
+    pub fn empty_str_policy(mut self, policy: OmitPolicy) -> Self {
+        self.empty_str_policy = policy;
+        self
+    }
+
+    /// Set what is written for an empty sequence, [`OmitPolicy::Omit`] by
+    /// default.
+    ///
+    /// With [`OmitPolicy::Null`], a `null` value is read back as an empty
+    /// sequence. With [`OmitPolicy::Empty`], a sequence holding a single empty
+    /// element is rejected, it would be read back as an empty sequence.
+    pub fn empty_seq_policy(mut self, policy: OmitPolicy) -> Self {
+        self.empty_seq_policy = policy;
+        self
+    }
+
+    /// Set what is written for an empty string and for an empty sequence.
+    pub fn empty_policy(self, policy: OmitPolicy) -> Self {
+        self.empty_str_policy(policy).empty_seq_policy(policy)
+    }
+
+    /// Set what is written for a `()`, [`OmitPolicy::Omit`] by default.
+    pub fn unit_policy(mut self, policy: OmitPolicy) -> Self {
+        self.unit_policy = policy;
+        self
+    }
+
+    /// Set the value written for [`OmitPolicy::Null`] and for the `Some(None)`
+    /// of an `Option<Option<T>>`, `null` by default.
+    pub fn null_token(mut self, token: impl Into<Cow<'static, str>>) -> Self {
+        self.null_token = token.into();
+        self
+    }
+
+    /// Set how the unit structs are written, [`UnitStructStyle::Unit`] by
+    /// default.
+    pub fn unit_struct_style(mut self, style: UnitStructStyle) -> Self {
+        self.unit_struct_style = style;
+        self
+    }
+
+    /// Set how the bytes are written and read, [`BytesEncoding::Numbers`] by
+    /// default.
+    pub fn bytes_encoding(mut self, encoding: BytesEncoding) -> Self {
+        self.bytes_encoding = encoding;
+        self
+    }
+
+    /// Set how the finite floats are written, [`FloatFormat::Shortest`] by
+    /// default.
+    pub fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Set how NaN and the infinite floats are written and read,
+    /// [`NonFiniteFloats::Display`] by default.
+    pub fn non_finite_floats(mut self, policy: NonFiniteFloats) -> Self {
+        self.non_finite_floats = policy;
+        self
+    }
+
+    /// Set how the bools are written and read, [`BoolStyle::TrueFalse`] by
+    /// default.
+    ///
+    /// ```
+    /// use nyaup::{BoolStyle, Config};
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Params {
+    ///     good: bool,
+    ///     hungry: bool,
+    /// }
+    ///
+    /// let params = Params { good: true, hungry: false };
+    /// let config = Config::new().bool_style(BoolStyle::Checkbox);
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?good=on");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    pub fn bool_style(mut self, style: BoolStyle) -> Self {
+        self.bool_style = style;
+        self
+    }
+
+    /// Set how the enums are written and read, [`EnumStyle::Name`] by
+    /// default.
+    ///
+    /// ```
+    /// use nyaup::{Config, EnumStyle};
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// enum Filter {
+    ///     Id(usize),
+    ///     Page(usize),
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Params {
+    ///     filter: Filter,
+    /// }
+    ///
+    /// let params = Params { filter: Filter::Page(3) };
+    /// let config = Config::new().enum_style(EnumStyle::TagParam("_type"));
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?filter=3&filter_type=Page");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    pub fn enum_style(mut self, style: EnumStyle) -> Self {
+        self.enum_style = style;
+        self
+    }
+
+    /// Set the case the variant names are converted to,
+    /// [`VariantCase::Unchanged`] by default.
+    pub fn variant_case(mut self, case: VariantCase) -> Self {
+        self.variant_case = case;
+        self
+    }
+
+    /// Write the variant of an enum serialized at the top level in a
+    /// parameter with the given key, it is dropped by default. The variant
+    /// can't hold a key equal to the tag.
+    ///
+    /// ```
+    /// use nyaup::Config;
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// enum Search {
+    ///     ByName { name: String },
+    ///     ById { id: usize },
+    /// }
+    ///
+    /// let search = Search::ByName { name: String::from("kefir") };
+    /// let config = Config::new().variant_tag("type");
+    /// let query = nyaup::to_string_with(&search, &config).unwrap();
+    /// assert_eq!(query, "?type=ByName&name=kefir");
+    /// assert_eq!(nyaup::from_str_with::<Search>(&query, &config).unwrap(), search);
+    /// ```
+    pub fn variant_tag(mut self, key: &'static str) -> Self {
+        self.variant_tag = Some(key);
+        self
+    }
+
+    /// Set how the values are percent-encoded, [`Encoding::Form`] by default.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Set how the sequences are written and read, [`SequenceStyle::Comma`] by
+    /// default.
+    pub fn sequence_style(mut self, style: SequenceStyle) -> Self {
+        self.sequence_style = style;
+        self
+    }
+
+    /// Set the delimiters of the sequences nested in a sequence, from the
+    /// outermost to the innermost, none by default.
+    ///
+    /// The sequences nested deeper than the given delimiters are rejected. A
+    /// nested delimiter must be made of ASCII punctuation other than `%`, `&`,
+    /// `=`, `#` or the separators of the parameters, and share no character
+    /// with the delimiters of the sequences holding it or the separator of
+    /// the inline pairs. An empty nested sequence is written as nothing, so a
+    /// nested sequence holding a single empty element is rejected.
+    ///
+    /// ```
+    /// use nyaup::{Config, SequenceStyle};
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Params {
+    ///     bounding_box: Vec<(f64, f64)>,
+    /// }
+    ///
+    /// let params = Params { bounding_box: vec![(1.0, 2.5), (3.0, 4.5)] };
+    /// let config = Config::new()
+    ///     .sequence_style(SequenceStyle::PipeDelimited)
+    ///     .nested_delimiters(&[","]);
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?bounding_box=1,2.5|3,4.5");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    pub fn nested_delimiters(mut self, delimiters: &'static [&'static str]) -> Self {
+        self.nested_delimiters = delimiters;
+        self
+    }
+
+    /// Write the maps, and the 2-tuples inside sequences, as `key:value` pairs
+    /// joined with the given separator, not supported by default.
+    ///
+    /// The maps are written as sequences of pairs, following the
+    /// [`SequenceStyle`]. The separator must be made of ASCII punctuation
+    /// other than `%`, `&`, `=`, `#` or the separators of the parameters, and
+    /// share no character with the delimiters of the sequences holding the
+    /// pairs.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use nyaup::Config;
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// #[serde(rename_all = "camelCase")]
+    /// struct Params {
+    ///     attributes_to_crop: BTreeMap<String, usize>,
+    ///     sort: Vec<(String, String)>,
+    /// }
+    ///
+    /// let params = Params {
+    ///     attributes_to_crop: BTreeMap::from([(String::from("overview"), 5)]),
+    ///     sort: vec![(String::from("price"), String::from("asc"))],
+    /// };
+    /// let config = Config::new().inline_pairs(":");
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?attributesToCrop=overview:5&sort=price:asc");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    pub fn inline_pairs(mut self, separator: &'static str) -> Self {
+        self.inline_pairs = Some(separator);
+        self
+    }
+
+    /// Set how the structs and maps nested in a value are written,
+    /// [`NestingStyle::Unsupported`] by default.
+    ///
+    /// ```
+    /// use nyaup::{Config, NestingStyle};
+    ///
+    /// #[derive(serde::Serialize)]
+    /// struct Range {
+    ///     gte: usize,
+    /// }
+    ///
+    /// #[derive(serde::Serialize)]
+    /// struct Filter {
+    ///     name: &'static str,
+    ///     age: Range,
+    /// }
+    ///
+    /// #[derive(serde::Serialize)]
+    /// struct Params {
+    ///     filter: Filter,
+    /// }
+    ///
+    /// let params = Params { filter: Filter { name: "kefir", age: Range { gte: 3 } } };
+    /// let config = Config::new().nesting_style(NestingStyle::Brackets);
+    /// assert_eq!(
+    ///     nyaup::to_string_with(&params, &config).unwrap(),
+    ///     "?filter[name]=kefir&filter[age][gte]=3",
+    /// );
+    /// ```
+    pub fn nesting_style(mut self, style: NestingStyle) -> Self {
+        self.nesting_style = style;
+        self
+    }
+
+    /// Set how the sequences of structs or maps are written and read,
+    /// [`StructSequenceStyle::Unsupported`] by default.
+    ///
+    /// ```
+    /// use nyaup::{Config, StructSequenceStyle};
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Item {
+    ///     id: usize,
+    ///     qty: usize,
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Params {
+    ///     items: Vec<Item>,
+    /// }
+    ///
+    /// let params = Params { items: vec![Item { id: 1, qty: 3 }, Item { id: 2, qty: 4 }] };
+    /// let config = Config::new().struct_sequence_style(StructSequenceStyle::Columns);
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?items.id=1,2&items.qty=3,4");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    pub fn struct_sequence_style(mut self, style: StructSequenceStyle) -> Self {
+        self.struct_sequence_style = style;
+        self
+    }
+
+    /// Send the values the format can't express, like nested structs or
+    /// nested sequences, as JSON, disabled by default.
+    ///
+    /// When deserializing, the sequences, maps and structs sent as a JSON
+    /// array or object are read as JSON. The values that would be mistaken for
+    /// JSON, like `vec!["[1]"]`, are then sent as JSON too.
+    ///
+    /// ```
+    /// use nyaup::Config;
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Variables {
+    ///     a: usize,
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// struct Params {
+    ///     query: String,
+    ///     variables: Variables,
+    /// }
+    ///
+    /// let params = Params { query: String::from("{ doggo }"), variables: Variables { a: 1 } };
+    /// let config = Config::new().json_fallback(true);
+    /// let query = nyaup::to_string_with(&params, &config).unwrap();
+    /// assert_eq!(query, "?query=%7B+doggo+%7D&variables=%7B%22a%22%3A1%7D");
+    /// assert_eq!(nyaup::from_str_with::<Params>(&query, &config).unwrap(), params);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json_fallback(mut self, enabled: bool) -> Self {
+        self.json_fallback = enabled;
+        self
+    }
+}
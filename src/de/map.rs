@@ -0,0 +1,208 @@
+use crate::config::{Config, EnumStyle, StructSequenceStyle};
+use crate::error::{Error, Result};
+use serde::de::IntoDeserializer;
+use std::borrow::Cow;
+
+use super::simple::{Pairs, Value};
+
+pub struct Deserializer<'a, 'de> {
+    pairs: std::vec::IntoIter<(&'de str, Value<'de>)>,
+    fields: std::slice::Iter<'static, &'static str>,
+    config: &'a Config,
+    seen: Vec<Cow<'de, str>>,
+    /// The decoded keys along with the raw variant sent in their tag
+    /// parameter.
+    tags: Vec<(Cow<'de, str>, &'de str)>,
+    value: Option<(Value<'de>, Option<&'de str>)>,
+    /// How many sequences the values are elements of.
+    seq_depth: usize,
+}
+
+impl<'de> Deserializer<'_, 'de> {
+    fn tag(&self, key: &str) -> Option<&'de str> {
+        self.tags
+            .iter()
+            .find(|(tagged, _)| tagged == key)
+            .map(|(_, tag)| *tag)
+    }
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    pub fn new(
+        pairs: Pairs<'de>,
+        fields: &'static [&'static str],
+        seq_depth: usize,
+        config: &'a Config,
+    ) -> Result<Self> {
+        let mut pairs = group_rows(pairs, fields, config)?;
+        let mut tags = Vec::new();
+        if let EnumStyle::TagParam(suffix) = config.enum_style {
+            let keys = pairs
+                .iter()
+                .map(|(key, _)| config.encoding.decode(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            // a tag parameter is only recognized next to the key or the field it is named after
+            let mut is_tag = Vec::with_capacity(pairs.len());
+            for (key, (_, value)) in keys.iter().zip(&pairs) {
+                let base = key
+                    .strip_suffix(suffix)
+                    .filter(|base| fields.contains(base) || keys.iter().any(|key| key == base));
+                match (base, value) {
+                    (Some(base), Value::Raw(tag)) => {
+                        tags.push((Cow::Owned(base.to_string()), *tag));
+                        is_tag.push(true);
+                    }
+                    _ => is_tag.push(false),
+                }
+            }
+            let mut is_tag = is_tag.into_iter();
+            pairs.retain(|_| !is_tag.next().unwrap());
+        }
+        Ok(Deserializer {
+            pairs: pairs.into_iter(),
+            fields: fields.iter(),
+            config,
+            seen: Vec::new(),
+            tags,
+            value: None,
+            seq_depth,
+        })
+    }
+}
+
+impl<'de> ::serde::de::MapAccess<'de> for Deserializer<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        if let Some((key, value)) = self.pairs.next() {
+            let decoded = self.config.encoding.decode(key)?;
+            self.value = Some((value, self.tag(&decoded)));
+            if self.fields.len() != 0 {
+                self.seen.push(decoded);
+            }
+            let simple = match self.seq_depth {
+                0 => super::simple::Deserializer::new_from_toplevel(Value::Raw(key), self.config),
+                // the key of an inline pair
+                depth => {
+                    super::simple::Deserializer::new_from_seq(Value::Raw(key), depth, self.config)
+                }
+            };
+            return seed.deserialize(simple).map(Some);
+        }
+
+        // The serializer omits the empty values, the struct fields that were not sent are
+        // given to the visitor as absent so they can still be read as `None` or empty.
+        for field in self.fields.by_ref() {
+            if !self.seen.iter().any(|seen| seen == field) {
+                self.value = Some((Value::Absent(field), self.tag(field)));
+                return seed.deserialize(field.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let (value, tag) = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("value is missing"))?;
+        if self.seq_depth != 0 {
+            return seed.deserialize(super::simple::Deserializer::new_from_seq(
+                value,
+                self.seq_depth,
+                self.config,
+            ));
+        }
+        seed.deserialize(super::simple::Deserializer::new_from_map(
+            value,
+            tag,
+            self.config,
+        ))
+    }
+}
+
+/// A field of an element of a sequence of structs, along with the index of
+/// the element.
+type Cell<'de> = (usize, &'de str, Value<'de>);
+
+/// Gather the parameters holding the fields of the elements of a sequence of
+/// structs into a single pair, named after the field holding the sequence.
+fn group_rows<'de>(pairs: Pairs<'de>, fields: &[&str], config: &Config) -> Result<Pairs<'de>> {
+    let style = config.struct_sequence_style;
+    if style == StructSequenceStyle::Unsupported {
+        return Ok(pairs);
+    }
+    let mut grouped = Vec::with_capacity(pairs.len());
+    // the position of each sequence in `grouped`, along with its cells
+    let mut sequences: Vec<(usize, Vec<Cell<'de>>)> = Vec::new();
+    for (key, value) in pairs {
+        let split = match style {
+            StructSequenceStyle::Indexed => super::split_brackets(key).and_then(|(rest, field)| {
+                let (base, index) = super::split_brackets(rest)?;
+                Some((base, Some(index.parse().ok()?), field))
+            }),
+            _ => key
+                .rsplit_once('.')
+                .map(|(base, field)| (base, None, field)),
+        };
+        let Some((base, index, field)) = split else {
+            grouped.push((key, value));
+            continue;
+        };
+        if !fields.contains(&&*config.encoding.decode(base)?) {
+            grouped.push((key, value));
+            continue;
+        }
+        let position = grouped.iter().position(|(key, _)| *key == base);
+        let cells = match sequences.iter_mut().find(|(p, _)| Some(*p) == position) {
+            Some((_, cells)) => cells,
+            None => {
+                sequences.push((grouped.len(), Vec::new()));
+                grouped.push((base, Value::Rows(Vec::new(), 0)));
+                &mut sequences.last_mut().unwrap().1
+            }
+        };
+        match (index, value) {
+            (Some(index), value) => cells.push((index, field, value)),
+            // a column holds the field of every element
+            (None, Value::Raw(raw)) => match config.delimiter(0) {
+                Some(delimiter) => cells.extend(
+                    raw.split(delimiter)
+                        .enumerate()
+                        .map(|(index, cell)| (index, field, Value::Raw(cell))),
+                ),
+                None => cells.push((0, field, Value::Raw(raw))),
+            },
+            (None, Value::Repeated(raws)) => cells.extend(
+                raws.into_iter()
+                    .enumerate()
+                    .map(|(index, cell)| (index, field, Value::Raw(cell))),
+            ),
+            (None, _) => (),
+        }
+    }
+    // the cells of a column are elements of the sequence of its field
+    let seq_depth = match style {
+        StructSequenceStyle::Columns => 1,
+        _ => 0,
+    };
+    for (position, mut cells) in sequences {
+        cells.sort_by_key(|(index, _, _)| *index);
+        let mut rows: Vec<(usize, Pairs<'de>)> = Vec::new();
+        for (index, field, value) in cells {
+            match rows.last_mut() {
+                Some((last, row)) if *last == index => row.push((field, value)),
+                _ => rows.push((index, vec![(field, value)])),
+            }
+        }
+        let rows = rows.into_iter().map(|(_, row)| row).collect();
+        grouped[position].1 = Value::Rows(rows, seq_depth);
+    }
+    Ok(grouped)
+}
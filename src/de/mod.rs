@@ -0,0 +1,474 @@
+//! Deserialize a URL parameters string into a Rust data structure.
+
+mod map;
+mod seq;
+mod simple;
+mod variant;
+
+use crate::config::{Config, SequenceStyle};
+use crate::error::{Error, Result};
+
+use self::simple::Value;
+
+/// A structure for deserializing URL parameters string into Rust values.
+pub struct Deserializer<'de> {
+    input: &'de str,
+    config: Config,
+    /// Whether the tag parameter of the enum being read is left out of its
+    /// value.
+    skip_variant_tag: bool,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Create a deserializer reading the given URL parameters string.
+    ///
+    /// The leading `?` is optional.
+    pub fn new(input: &'de str) -> Self {
+        Self::with_config(input, Config::default())
+    }
+
+    /// Create a deserializer reading the given URL parameters string with the
+    /// given [`Config`].
+    ///
+    /// The prefix of the configuration is optional.
+    pub fn with_config(input: &'de str, config: Config) -> Self {
+        Deserializer {
+            input: input.strip_prefix(&*config.prefix).unwrap_or(input),
+            config,
+            skip_variant_tag: false,
+        }
+    }
+
+    /// Split the input into its raw, still encoded, `key=value` pairs.
+    ///
+    /// When the sequences are sent as one parameter per element, the values
+    /// sharing a key are gathered in a single pair.
+    fn pairs(&self) -> Vec<(&'de str, Value<'de>)> {
+        let pairs = self
+            .input
+            .split(self.config.pair_separator)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                pair.split_once(self.config.key_value_separator)
+                    .unwrap_or((pair, ""))
+            })
+            .filter(|(key, _)| !self.skip_variant_tag || !self.is_variant_tag(key));
+
+        let style = self.config.sequence_style;
+        if style.delimiter().is_some() {
+            return pairs.map(|(key, value)| (key, Value::Raw(value))).collect();
+        }
+
+        let mut grouped: Vec<(&'de str, Vec<(usize, &'de str)>)> = Vec::new();
+        for (raw, value) in pairs {
+            let (key, index) = match style {
+                SequenceStyle::Brackets => match split_brackets(raw) {
+                    Some((key, "")) => (key, 0),
+                    _ => (raw, 0),
+                },
+                SequenceStyle::Indexed => split_brackets(raw)
+                    .and_then(|(key, index)| Some((key, index.parse().ok()?)))
+                    .unwrap_or((raw, 0)),
+                _ => (raw, 0),
+            };
+            match grouped.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, values)) => values.push((index, value)),
+                None => grouped.push((key, vec![(index, value)])),
+            }
+        }
+        grouped
+            .into_iter()
+            .map(|(key, mut values)| {
+                values.sort_by_key(|(index, _)| *index);
+                let values = values.into_iter().map(|(_, value)| value).collect();
+                (key, Value::Repeated(values))
+            })
+            .collect()
+    }
+}
+
+impl Deserializer<'_> {
+    fn is_variant_tag(&self, raw: &str) -> bool {
+        match self.config.variant_tag {
+            Some(tag) => self.config.encoding.decode(raw).is_ok_and(|key| key == tag),
+            None => false,
+        }
+    }
+}
+
+/// Split a raw `key[inner]` into `key` and `inner`, the brackets may be
+/// percent-encoded.
+pub(crate) fn split_brackets(raw: &str) -> Option<(&str, &str)> {
+    let raw = match raw.strip_suffix(']') {
+        Some(raw) => raw,
+        None => {
+            let (raw, bracket) = raw.split_at_checked(raw.len().checked_sub(3)?)?;
+            if !bracket.eq_ignore_ascii_case("%5D") {
+                return None;
+            }
+            raw
+        }
+    };
+    if let Some((key, inner)) = raw.rsplit_once('[') {
+        return Some((key, inner));
+    }
+    // the ascii uppercase conversion doesn't move any byte
+    let position = raw.to_ascii_uppercase().rfind("%5B")?;
+    Some((&raw[..position], &raw[position + 3..]))
+}
+
+impl<'de> ::serde::de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    #[inline]
+    fn deserialize_bool<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("bool"))
+    }
+
+    #[inline]
+    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("i8"))
+    }
+
+    #[inline]
+    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("i16"))
+    }
+
+    #[inline]
+    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("i32"))
+    }
+
+    #[inline]
+    fn deserialize_i64<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("i64"))
+    }
+
+    #[inline]
+    fn deserialize_i128<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("i128"))
+    }
+
+    #[inline]
+    fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("u8"))
+    }
+
+    #[inline]
+    fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("u16"))
+    }
+
+    #[inline]
+    fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("u32"))
+    }
+
+    #[inline]
+    fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("u64"))
+    }
+
+    #[inline]
+    fn deserialize_u128<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("u128"))
+    }
+
+    #[inline]
+    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("f32"))
+    }
+
+    #[inline]
+    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("f64"))
+    }
+
+    #[inline]
+    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("char"))
+    }
+
+    #[inline]
+    fn deserialize_str<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("str"))
+    }
+
+    #[inline]
+    fn deserialize_string<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("string"))
+    }
+
+    #[inline]
+    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("bytes"))
+    }
+
+    #[inline]
+    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("bytes"))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // `to_string(&None)` doesn't write anything, not even the `?`.
+        if self.input.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    #[inline]
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    #[inline]
+    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("unit struct"))
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("sequence"))
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("tuple"))
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("tuple struct"))
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_map(map::Deserializer::new(self.pairs(), &[], 0, &self.config)?)
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_map(map::Deserializer::new(
+            self.pairs(),
+            fields,
+            0,
+            &self.config,
+        )?)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let Some(tag) = self.config.variant_tag else {
+            return Err(Error::DeserializeAtTopLevel("enum"));
+        };
+        let raw = self
+            .input
+            .split(self.config.pair_separator)
+            .filter_map(|pair| pair.split_once(self.config.key_value_separator))
+            .find(|(key, _)| self.is_variant_tag(key))
+            .map(|(_, value)| value)
+            .ok_or_else(|| <Error as serde::de::Error>::missing_field(tag))?;
+        let variant = variant::Variant::tagged(raw, variants, &self.config)?;
+        visitor.visit_enum(variant::Tagged::new(variant, self))
+    }
+
+    #[inline]
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("identifier"))
+    }
+
+    #[inline]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserialize an instance of type `T` from a string of URL parameters.
+///
+/// # Errors
+///
+/// Deserialization fails if:
+///
+/// * `T`'s implementation of `Deserialize` decides to fail,
+/// * `T` is a type without keys, i.e. not a struct.
+/// * a value can't be parsed as the type of its field,
+/// * a key or a value isn't valid UTF-8 once percent-decoded.
+#[inline]
+pub fn from_str<'de, T>(input: &'de str) -> Result<T>
+where
+    T: ::serde::de::Deserialize<'de>,
+{
+    from_str_with(input, &Config::default())
+}
+
+/// Deserialize an instance of type `T` from bytes of URL parameters.
+///
+/// # Errors
+///
+/// Deserialization fails if:
+///
+/// * `input` isn't valid UTF-8,
+/// * `T`'s implementation of `Deserialize` decides to fail,
+/// * `T` is a type without keys, i.e. not a struct.
+/// * a value can't be parsed as the type of its field,
+/// * a key or a value isn't valid UTF-8 once percent-decoded.
+#[inline]
+pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T>
+where
+    T: ::serde::de::Deserialize<'de>,
+{
+    let input = std::str::from_utf8(input)?;
+    from_str(input)
+}
+
+/// Deserialize an instance of type `T` from a string of URL parameters,
+/// following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`from_str`].
+#[inline]
+pub fn from_str_with<'de, T>(input: &'de str, config: &Config) -> Result<T>
+where
+    T: ::serde::de::Deserialize<'de>,
+{
+    let mut de = Deserializer::with_config(input, config.clone());
+    T::deserialize(&mut de)
+}
+
+/// Deserialize an instance of type `T` from bytes of URL parameters,
+/// following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`from_bytes`].
+#[inline]
+pub fn from_bytes_with<'de, T>(input: &'de [u8], config: &Config) -> Result<T>
+where
+    T: ::serde::de::Deserialize<'de>,
+{
+    let input = std::str::from_utf8(input)?;
+    from_str_with(input, config)
+}
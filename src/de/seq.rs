@@ -0,0 +1,43 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+use super::simple::Value;
+
+pub struct Deserializer<'a, 'de> {
+    elements: std::vec::IntoIter<Value<'de>>,
+    /// How many sequences the elements are part of.
+    depth: usize,
+    config: &'a Config,
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    pub fn new(elements: Vec<Value<'de>>, depth: usize, config: &'a Config) -> Self {
+        Deserializer {
+            elements: elements.into_iter(),
+            depth,
+            config,
+        }
+    }
+}
+
+impl<'de> ::serde::de::SeqAccess<'de> for Deserializer<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        match self.elements.next() {
+            Some(element) => {
+                let simple =
+                    super::simple::Deserializer::new_from_seq(element, self.depth, self.config);
+                seed.deserialize(simple).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
@@ -0,0 +1,612 @@
+//! Internal deserializer for simple value
+
+use std::borrow::Cow;
+
+use serde::de::{Error as _, Unexpected};
+
+use super::variant::Variant;
+
+use crate::config::{BoolStyle, BytesEncoding, Config, Encoding, EnumStyle, OmitPolicy};
+use crate::error::{Error, Result};
+
+/// A value as it appears on the right-hand side of a parameter.
+pub enum Value<'de> {
+    /// The raw, still encoded, value.
+    Raw(&'de str),
+    /// The raw values of a key sent once per element of a sequence.
+    Repeated(Vec<&'de str>),
+    /// The struct field was not sent at all.
+    Absent(&'static str),
+    /// The elements of a sequence of structs, their fields being the elements
+    /// of `usize` sequences.
+    Rows(Vec<Pairs<'de>>, usize),
+    /// An element of a sequence of structs, its fields being the elements of
+    /// `usize` sequences.
+    Fields(Pairs<'de>, usize),
+}
+
+/// The raw keys of the parameters along with their values.
+pub type Pairs<'de> = Vec<(&'de str, Value<'de>)>;
+
+pub struct Deserializer<'a, 'de> {
+    value: Value<'de>,
+    /// The raw variant sent in its own parameter with [`EnumStyle::TagParam`].
+    tag: Option<&'de str>,
+    config: &'a Config,
+    /// How many sequences the value is an element of.
+    seq_depth: usize,
+    /// Whether the value is read inside a `Some`, the null token is then
+    /// read as an explicit `None`.
+    in_some: bool,
+    /// Whether the value follows the prefix of its variant, an empty sequence
+    /// then leaving it empty.
+    after_prefix: bool,
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    pub fn new_from_toplevel(value: Value<'de>, config: &'a Config) -> Self {
+        Deserializer {
+            value,
+            tag: None,
+            config,
+            seq_depth: 0,
+            in_some: false,
+            after_prefix: false,
+        }
+    }
+
+    /// The value of a map, along with the variant sent in its own parameter.
+    pub fn new_from_map(value: Value<'de>, tag: Option<&'de str>, config: &'a Config) -> Self {
+        Deserializer {
+            value,
+            tag,
+            config,
+            seq_depth: 0,
+            in_some: false,
+            after_prefix: false,
+        }
+    }
+
+    /// The value of a variant, written after its prefix.
+    pub fn new_from_prefix(value: Value<'de>, config: &'a Config) -> Self {
+        Deserializer {
+            after_prefix: true,
+            ..Self::new_from_toplevel(value, config)
+        }
+    }
+
+    pub fn new_from_seq(value: Value<'de>, seq_depth: usize, config: &'a Config) -> Self {
+        Deserializer {
+            value,
+            tag: None,
+            config,
+            seq_depth,
+            in_some: false,
+            after_prefix: false,
+        }
+    }
+
+    /// Whether a sequence can be read in place of the value.
+    fn sequence_allowed(&self) -> bool {
+        self.seq_depth == 0 || self.config.delimiter(self.seq_depth).is_some()
+    }
+
+    fn raw(&self) -> Result<&'de str> {
+        match self.value {
+            Value::Raw(raw) => Ok(raw),
+            Value::Repeated(ref raws) if raws.len() == 1 => Ok(raws[0]),
+            Value::Repeated(ref raws) => Err(Error::invalid_length(raws.len(), &"a single value")),
+            Value::Absent(field) => Err(Error::missing_field(field)),
+            Value::Rows(..) => Err(Error::invalid_type(Unexpected::Seq, &"a single value")),
+            Value::Fields(..) => Err(Error::invalid_type(Unexpected::Map, &"a single value")),
+        }
+    }
+
+    fn encoding(&self) -> Encoding {
+        // the spaces of the elements delimited by `%20` are sent as `+`
+        if self.seq_depth != 0 && self.config.plus_is_space_in_elements() {
+            return Encoding::Form;
+        }
+        self.config.encoding
+    }
+
+    fn decode(&self, raw: &'de str) -> Result<Cow<'de, str>> {
+        Ok(self.encoding().decode(raw)?)
+    }
+
+    fn decoded(&self) -> Result<Cow<'de, str>> {
+        self.decode(self.raw()?)
+    }
+
+    /// Find the variant whose name, converted to the case of the
+    /// configuration, or whose index with [`EnumStyle::Index`], was sent.
+    fn variant(&self, raw: &'de str, variants: &'static [&'static str]) -> Result<Variant<'de>> {
+        let name = self.decode(raw)?;
+        if self.config.enum_style == EnumStyle::Index {
+            return name
+                .parse()
+                .map(Variant::Index)
+                .map_err(|_| Error::invalid_value(Unexpected::Str(&name), &"a variant index"));
+        }
+        Ok(Variant::named(name, variants, self.config))
+    }
+
+    /// Split the raw value into the raw elements of a sequence.
+    fn elements(&self) -> Result<Vec<&'de str>> {
+        let null_is_empty = self.config.empty_seq_policy == OmitPolicy::Null;
+        // the empty sequences are left out of their own parameter, unless written empty
+        let empty_is_empty = self.seq_depth != 0
+            || self.after_prefix
+            || self.config.empty_seq_policy == OmitPolicy::Empty;
+        Ok(match self.value {
+            Value::Raw("") if empty_is_empty => Vec::new(),
+            Value::Repeated(ref raws) if empty_is_empty && raws[..] == [""] => Vec::new(),
+            Value::Raw(raw) if null_is_empty && self.is_null(raw) => Vec::new(),
+            Value::Repeated(ref raws)
+                if null_is_empty && matches!(raws[..], [raw] if self.is_null(raw)) =>
+            {
+                Vec::new()
+            }
+            Value::Raw(raw) => match self.config.delimiter(self.seq_depth) {
+                Some(delimiter) => raw.split(delimiter).collect(),
+                None => vec![raw],
+            },
+            Value::Repeated(ref raws) => raws.clone(),
+            // empty sequences are not sent by default
+            Value::Absent(_) => Vec::new(),
+            Value::Rows(..) | Value::Fields(..) => {
+                return Err(Error::DeserializeNestedStruct("sequence"))
+            }
+        })
+    }
+
+    /// Split an inline pair into its raw key and value.
+    fn split_pair(&self, raw: &'de str) -> Result<(&'de str, &'de str)> {
+        let separator = self.config.inline_pairs.unwrap_or_default();
+        raw.split_once(separator)
+            .ok_or_else(|| Error::invalid_value(Unexpected::Str(raw), &"a key-value pair"))
+    }
+
+    /// The value sent as a JSON array or object, when the configuration falls
+    /// back to JSON.
+    #[cfg(feature = "json")]
+    fn json(&self) -> Option<serde_json::Value> {
+        // only the whole value of a parameter is sent as JSON
+        if !self.config.json_fallback || self.seq_depth != 0 || self.after_prefix {
+            return None;
+        }
+        let decoded = match self.value {
+            Value::Raw(_) | Value::Repeated(_) => self.decoded().ok()?,
+            _ => return None,
+        };
+        if !decoded.starts_with(['[', '{']) {
+            return None;
+        }
+        serde_json::from_str(&decoded).ok()
+    }
+
+    /// Whether the raw value is the null token of the configuration.
+    fn is_null(&self, raw: &'de str) -> bool {
+        self.decode(raw)
+            .is_ok_and(|value| value == self.config.null_token)
+    }
+
+    fn parse_float<F>(&self, expected: &'static str) -> Result<F>
+    where
+        F: std::str::FromStr + From<f32>,
+    {
+        let v = self.decoded()?;
+        self.config
+            .parse_float(&v)
+            .ok_or_else(|| Error::invalid_value(Unexpected::Str(&v), &expected))
+    }
+
+    fn parse<T>(&self, expected: &'static str) -> Result<T>
+    where
+        T: std::str::FromStr,
+    {
+        let v = self.decoded()?;
+        v.parse()
+            .map_err(|_| Error::invalid_value(Unexpected::Str(&v), &expected))
+    }
+}
+
+impl<'de> ::serde::de::Deserializer<'de> for Deserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Raw(_) | Value::Repeated(_) => self.deserialize_str(visitor),
+            Value::Absent(_) => visitor.visit_none(),
+            Value::Rows(..) => self.deserialize_seq(visitor),
+            Value::Fields(..) => self.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let style = self.config.bool_style;
+        // an unchecked checkbox is not sent
+        if let (BoolStyle::Checkbox, Value::Absent(_)) = (style, &self.value) {
+            return visitor.visit_bool(false);
+        }
+        let v = self.decoded()?;
+        let v = style
+            .parse(&v)
+            .ok_or_else(|| Error::invalid_value(Unexpected::Str(&v), &"a boolean"))?;
+        visitor.visit_bool(v)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_i8(self.parse("an i8")?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_i16(self.parse("an i16")?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_i32(self.parse("an i32")?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_i64(self.parse("an i64")?)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse("an i128")?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_u8(self.parse("a u8")?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_u16(self.parse("a u16")?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_u32(self.parse("a u32")?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_u64(self.parse("a u64")?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse("a u128")?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_f32(self.parse_float("an f32")?)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_f64(self.parse_float("an f64")?)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_char(self.parse("a character")?)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // empty strings are not sent by default
+        if let Value::Absent(_) = self.value {
+            return visitor.visit_borrowed_str("");
+        }
+        let raw = self.raw()?;
+        if self.config.empty_str_policy == OmitPolicy::Null && self.is_null(raw) {
+            return visitor.visit_borrowed_str("");
+        }
+        match self.decode(raw)? {
+            Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
+            Cow::Owned(v) => visitor.visit_string(v),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let bytes_encoding = self.config.bytes_encoding;
+        if bytes_encoding != BytesEncoding::Numbers {
+            // empty bytes are not sent by default
+            let raw = match self.value {
+                Value::Absent(_) => "",
+                _ => self.raw()?,
+            };
+            let bytes = bytes_encoding
+                .decode(raw, self.encoding())
+                .ok_or_else(|| Error::invalid_value(Unexpected::Str(raw), &"encoded bytes"))?;
+            return visitor.visit_byte_buf(bytes);
+        }
+        if !self.sequence_allowed() {
+            return Err(Error::DeserializeNestedStruct("bytes"));
+        }
+        // bytes are sent as a sequence of numbers
+        let bytes: Vec<u8> = serde::de::Deserialize::deserialize(self)?;
+        visitor.visit_byte_buf(bytes)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let raw = match self.value {
+            Value::Raw(raw) => Some(raw),
+            Value::Repeated(ref raws) if raws.len() == 1 => Some(raws[0]),
+            Value::Repeated(_) => None,
+            Value::Absent(_) => return visitor.visit_none(),
+            Value::Rows(..) | Value::Fields(..) => return visitor.visit_some(self),
+        };
+        // an omitted element of a sequence still leaves an empty element behind
+        let element = self.seq_depth != 0;
+        let null = raw.is_some_and(|raw| self.is_null(raw));
+        match (self.config.none_policy, raw) {
+            // the `Some(None)` of an `Option<Option<T>>`
+            _ if self.in_some && null => visitor.visit_none(),
+            (OmitPolicy::Null, _) if null => visitor.visit_none(),
+            (OmitPolicy::Empty, Some("")) => visitor.visit_none(),
+            (OmitPolicy::Omit, Some("")) if element => visitor.visit_none(),
+            _ => {
+                self.in_some = true;
+                visitor.visit_some(self)
+            }
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // whether it was omitted or sent as its name, there is nothing to read
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        #[cfg(feature = "json")]
+        if let Some(json) = self.json() {
+            return Ok(serde::de::Deserializer::deserialize_seq(json, visitor)?);
+        }
+        if !self.sequence_allowed() {
+            return Err(Error::DeserializeNestedStruct("sequence"));
+        }
+        if let Value::Rows(rows, seq_depth) = self.value {
+            let elements = rows
+                .into_iter()
+                .map(|row| Value::Fields(row, seq_depth))
+                .collect();
+            return visitor.visit_seq(super::seq::Deserializer::new(
+                elements,
+                self.seq_depth + 1,
+                self.config,
+            ));
+        }
+        let raws = self.elements()?;
+        visitor.visit_seq(super::seq::Deserializer::new(
+            raws.into_iter().map(Value::Raw).collect(),
+            self.seq_depth + 1,
+            self.config,
+        ))
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // the 2-tuples inside a sequence are sent as inline pairs
+        if len == 2 && self.seq_depth != 0 && self.config.inline_pairs.is_some() {
+            let (key, value) = self.split_pair(self.raw()?)?;
+            return visitor.visit_seq(super::seq::Deserializer::new(
+                vec![Value::Raw(key), Value::Raw(value)],
+                self.seq_depth,
+                self.config,
+            ));
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        #[cfg(feature = "json")]
+        if let Some(json) = self.json() {
+            return Ok(serde::de::Deserializer::deserialize_map(json, visitor)?);
+        }
+        match self.value {
+            Value::Fields(pairs, seq_depth) => visitor.visit_map(super::map::Deserializer::new(
+                pairs,
+                &[],
+                seq_depth,
+                self.config,
+            )?),
+            // a map sent as inline pairs is a sequence of pairs
+            _ if self.config.inline_pairs.is_some() && self.sequence_allowed() => {
+                let pairs = self
+                    .elements()?
+                    .into_iter()
+                    .map(|element| {
+                        let (key, value) = self.split_pair(element)?;
+                        Ok((key, Value::Raw(value)))
+                    })
+                    .collect::<Result<_>>()?;
+                visitor.visit_map(super::map::Deserializer::new(
+                    pairs,
+                    &[],
+                    self.seq_depth + 1,
+                    self.config,
+                )?)
+            }
+            _ => Err(Error::DeserializeNestedStruct("map")),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        #[cfg(feature = "json")]
+        if let Some(json) = self.json() {
+            return Ok(serde::de::Deserializer::deserialize_any(json, visitor)?);
+        }
+        match self.value {
+            Value::Fields(pairs, seq_depth) => visitor.visit_map(super::map::Deserializer::new(
+                pairs,
+                fields,
+                seq_depth,
+                self.config,
+            )?),
+            _ => Err(Error::DeserializeNestedStruct("struct")),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let (variant, value) = match self.config.enum_style {
+            // only the unit variants can be read, the others were sent as their value
+            EnumStyle::Name => (self.variant(self.raw()?, variants)?, None),
+            EnumStyle::Index | EnumStyle::Prefixed => match self.raw()?.split_once(':') {
+                Some((variant, first)) => {
+                    let value = match self.value {
+                        Value::Repeated(ref raws) => Value::Repeated(
+                            [first]
+                                .into_iter()
+                                .chain(raws[1..].iter().copied())
+                                .collect(),
+                        ),
+                        _ => Value::Raw(first),
+                    };
+                    (self.variant(variant, variants)?, Some(value))
+                }
+                None => (self.variant(self.raw()?, variants)?, None),
+            },
+            EnumStyle::TagParam(_) => match self.tag {
+                Some(tag) => (self.variant(tag, variants)?, Some(self.value)),
+                None => (self.variant(self.raw()?, variants)?, None),
+            },
+        };
+        visitor.visit_enum(super::variant::Deserializer::new(
+            variant,
+            value,
+            self.config,
+            self.seq_depth,
+        ))
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
@@ -0,0 +1,204 @@
+//! Internal deserializer for the variant of an enum
+
+use std::borrow::Cow;
+
+use serde::de::{Error as _, IntoDeserializer, Unexpected};
+
+use crate::config::{Config, EnumStyle};
+use crate::error::{Error, Result};
+
+use super::simple::Value;
+
+/// How the variant was sent.
+pub enum Variant<'de> {
+    Name(Cow<'de, str>),
+    Index(u32),
+}
+
+impl<'de> Variant<'de> {
+    /// Find the variant whose name, converted to the case of the
+    /// configuration, was sent.
+    pub fn named(name: Cow<'de, str>, variants: &'static [&'static str], config: &Config) -> Self {
+        let case = config.variant_case;
+        let variant = variants
+            .iter()
+            .find(|variant| case.apply(variant) == name)
+            .map_or(name, |variant| Cow::Borrowed(*variant));
+        Variant::Name(variant)
+    }
+
+    /// Read the variant sent in the tag parameter of the top level.
+    pub fn tagged(
+        raw: &'de str,
+        variants: &'static [&'static str],
+        config: &Config,
+    ) -> Result<Self> {
+        let name = config.encoding.decode(raw)?;
+        if config.enum_style != EnumStyle::Index {
+            return Ok(Self::named(name, variants, config));
+        }
+        name.parse()
+            .map(Variant::Index)
+            .map_err(|_| Error::invalid_value(Unexpected::Str(&name), &"a variant index"))
+    }
+
+    /// Read the variant with the seed of an enum.
+    fn deserialize<V>(&self, seed: V) -> Result<V::Value>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        match self {
+            Variant::Name(name) => {
+                let name: serde::de::value::CowStrDeserializer<Error> =
+                    name.clone().into_deserializer();
+                seed.deserialize(name)
+            }
+            Variant::Index(index) => {
+                let index: serde::de::value::U32Deserializer<Error> = (*index).into_deserializer();
+                seed.deserialize(index)
+            }
+        }
+    }
+}
+
+pub struct Deserializer<'a, 'de> {
+    variant: Variant<'de>,
+    /// The value held by the variant, if any was sent.
+    value: Option<Value<'de>>,
+    config: &'a Config,
+    /// How many sequences the enum is an element of.
+    seq_depth: usize,
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    pub fn new(
+        variant: Variant<'de>,
+        value: Option<Value<'de>>,
+        config: &'a Config,
+        seq_depth: usize,
+    ) -> Self {
+        Deserializer {
+            variant,
+            value,
+            config,
+            seq_depth,
+        }
+    }
+
+    fn value(self) -> Result<super::simple::Deserializer<'a, 'de>> {
+        let value = self.value.ok_or_else(|| {
+            Error::invalid_type(Unexpected::UnitVariant, &"a variant with a value")
+        })?;
+        let prefixed = matches!(
+            self.config.enum_style,
+            EnumStyle::Prefixed | EnumStyle::Index
+        );
+        Ok(match self.seq_depth {
+            0 if prefixed => super::simple::Deserializer::new_from_prefix(value, self.config),
+            0 => super::simple::Deserializer::new_from_toplevel(value, self.config),
+            depth => super::simple::Deserializer::new_from_seq(value, depth, self.config),
+        })
+    }
+}
+
+impl<'a, 'de> ::serde::de::EnumAccess<'de> for Deserializer<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let variant = self.variant.deserialize(seed)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> ::serde::de::VariantAccess<'de> for Deserializer<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value()?)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        serde::de::Deserializer::deserialize_seq(self.value()?, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeNestedStruct("struct variant"))
+    }
+}
+
+/// An enum at the top level, its variant is sent in the tag parameter and its
+/// value in the other parameters.
+pub struct Tagged<'a, 'de> {
+    variant: Variant<'de>,
+    deserializer: &'a mut super::Deserializer<'de>,
+}
+
+impl<'a, 'de> Tagged<'a, 'de> {
+    pub fn new(variant: Variant<'de>, deserializer: &'a mut super::Deserializer<'de>) -> Self {
+        Tagged {
+            variant,
+            deserializer,
+        }
+    }
+}
+
+impl<'a, 'de> ::serde::de::EnumAccess<'de> for Tagged<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let variant = self.variant.deserialize(seed)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> ::serde::de::VariantAccess<'de> for Tagged<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        self.deserializer.skip_variant_tag = true;
+        seed.deserialize(self.deserializer)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error::DeserializeAtTopLevel("tuple variant"))
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserializer.skip_variant_tag = true;
+        serde::de::Deserializer::deserialize_struct(self.deserializer, "", fields, visitor)
+    }
+}
@@ -1,8 +1,8 @@
-//! When serializing to URL parameters fails.
+//! When serializing to or deserializing from URL parameters fails.
 
 #[derive(Debug, thiserror::Error)]
-/// Represents all possible errors that can occur when serializing into URL
-/// parameters.
+/// Represents all possible errors that can occur when serializing into or
+/// deserializing from URL parameters.
 pub enum Error {
     /// External error caused by e.g. utf8 string conversion or io.
     #[error(transparent)]
@@ -13,6 +13,38 @@ pub enum Error {
     /// Error when trying to serialize a key-value in place of a simple value.
     #[error("Tried to serialize a {0} in place of a value. Only simple values are supported on the right-hand side of a parameter.")]
     UnsupportedNestedStruct(&'static str),
+    /// Error when trying to serialize a key-value or a unit as the key of a
+    /// map.
+    #[error("Tried to serialize a {0} as a key. Only simple values and sequences of simple values are supported on the left-hand side of a parameter.")]
+    UnsupportedKey(&'static str),
+    /// Error when the delimiter of a sequence can't be told apart from the
+    /// content of its elements.
+    #[error("Tried to join the elements of a sequence with `{0}`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.")]
+    UnsupportedDelimiter(&'static str),
+    /// Error when a separator of the parameters could be written as-is inside
+    /// a key or a value.
+    #[error("Tried to separate the parameters with `{0}`. Only separators the encoding never writes as-is, other than `[`, `]`, `%`, `+`, a space, or `:` with the prefixed or indexed variants, and different from each other, are supported.")]
+    UnsupportedSeparator(char),
+    /// Error when a character kept as-is by [`Encoding::Custom`](crate::Encoding::Custom)
+    /// would end a parameter or couldn't be decoded.
+    #[error("Tried to keep `{0}` as-is in the encoding. Only ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters can be kept.")]
+    UnsupportedSafeChar(char),
+    /// Error when a top-level key is the variant tag written along with it.
+    #[error("Tried to serialize a parameter named `{0}` along with the variant tag. The keys of a tagged variant can't be its tag.")]
+    ConflictingVariantTag(&'static str),
+    /// Error when a raw value contains a character ending its parameter.
+    #[error("Tried to write a raw value containing `{0}`. Raw values can't contain `&`, `=`, `#` or the separators of the parameters.")]
+    UnsafeRawValue(char),
+    /// Error when a float can't be written with the
+    /// [`NonFiniteFloats`](crate::NonFiniteFloats) of the configuration.
+    #[error("Tried to serialize `{0}`. Only finite floats are supported unless tokens are configured for the others.")]
+    NonFiniteFloat(f64),
+    /// Error when trying to deserialize a value without any key.
+    #[error("Tried to deserialize a {0} at the top level. Only key-value shapes are supported at the top level of a query parameter.")]
+    DeserializeAtTopLevel(&'static str),
+    /// Error when trying to deserialize a key-value in place of a simple value.
+    #[error("Tried to deserialize a {0} in place of a value. Only simple values are supported on the right-hand side of a parameter.")]
+    DeserializeNestedStruct(&'static str),
     /// Custom user defined error
     #[error("{0}")]
     Custom(String),
@@ -33,6 +65,19 @@ impl From<std::string::FromUtf8Error> for Error {
     }
 }
 
+impl From<std::str::Utf8Error> for Error {
+    fn from(err: std::str::Utf8Error) -> Self {
+        Error::Extern(Box::new(err))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Extern(Box::new(err))
+    }
+}
+
 impl serde::ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
@@ -41,3 +86,12 @@ impl serde::ser::Error for Error {
         Error::Custom(msg.to_string())
     }
 }
+
+impl serde::de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Error::Custom(msg.to_string())
+    }
+}
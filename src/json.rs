@@ -0,0 +1,57 @@
+//! A value sent as JSON.
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value written as a percent-encoded JSON string, whatever the
+/// [`Config`](crate::Config).
+///
+/// ```
+/// use nyaup::Json;
+///
+/// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct Filter {
+///     breed: String,
+///     ages: Vec<u8>,
+/// }
+///
+/// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct Params {
+///     filter: Json<Filter>,
+/// }
+///
+/// let params = Params { filter: Json(Filter { breed: String::from("shiba"), ages: vec![2, 3] }) };
+/// let query = nyaup::to_string(&params).unwrap();
+/// assert_eq!(query, "?filter=%7B%22breed%22%3A%22shiba%22%2C%22ages%22%3A%5B2%2C3%5D%7D");
+/// assert_eq!(nyaup::from_str::<Params>(&query).unwrap(), params);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T> Serialize for Json<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let json = serde_json::to_string(&self.0).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Json<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let json = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        serde_json::from_str(&json)
+            .map(Json)
+            .map_err(D::Error::custom)
+    }
+}
@@ -1,18 +1,46 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 
+#[doc(inline)]
+pub use self::config::{
+    BoolStyle, BytesEncoding, Compat, Config, Encoding, EnumStyle, FloatFormat, NestingStyle,
+    NonFiniteFloats, OmitPolicy, SequenceStyle, StructSequenceStyle, UnitStructStyle, VariantCase,
+};
+#[doc(inline)]
+pub use self::de::{from_bytes, from_bytes_with, from_str, from_str_with, Deserializer};
 #[doc(inline)]
 pub use self::error::{Error, Result};
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use self::json::Json;
+#[doc(inline)]
+pub use self::nullable::Nullable;
 #[doc(inline)]
-pub use self::ser::{to_string, to_vec, to_writer, Serializer};
+pub use self::raw::Raw;
+#[doc(inline)]
+pub use self::ser::{
+    to_string, to_string_many, to_string_many_with, to_string_with, to_vec, to_vec_with, to_writer,
+    to_writer_with, Serializer,
+};
 
+mod config;
+mod de;
 mod error;
+#[cfg(feature = "json")]
+mod json;
+mod nullable;
+mod raw;
 mod ser;
 
 #[cfg(test)]
 mod tests {
-    use super::to_string;
-    use serde::Serialize;
+    use super::{
+        from_str, from_str_with, to_string, to_string_many, to_string_many_with, to_string_with,
+        to_writer, BoolStyle, BytesEncoding, Compat, Config, Encoding, EnumStyle, FloatFormat,
+        NestingStyle, NonFiniteFloats, Nullable, OmitPolicy, Raw, SequenceStyle,
+        StructSequenceStyle, UnitStructStyle, VariantCase,
+    };
+    use serde::{Deserialize, Serialize};
 
     #[derive(Debug, Serialize)]
     enum Selection {
@@ -91,7 +119,57 @@ mod tests {
             field: TupleStruct(42, "hello", 3.15),
         };
         let url_params = to_string(&params);
-        insta::assert_snapshot!(url_params.unwrap_err(), @"Tried to serialize a tuple struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        insta::assert_snapshot!(url_params.unwrap(), @"?field=42,hello,3.15");
+    }
+
+    #[test]
+    fn test_data_model() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+        struct Point(i32, i32);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Latest;
+
+        #[derive(Debug, PartialEq, Serialize)]
+        enum Shape {
+            Segment(Point, Point),
+            Circle(i32, i32, u32),
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            id: u128,
+            offset: i128,
+            point: Point,
+            version: Latest,
+        }
+
+        let params = Params {
+            id: u128::MAX,
+            offset: i128::MIN,
+            point: Point(-1, 2),
+            version: Latest,
+        };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?id=340282366920938463463374607431768211455&offset=-170141183460469231731687303715884105728&point=-1,2");
+        assert_eq!(from_str::<Params>(&url_params).unwrap(), params);
+
+        let config = Config::new().unit_struct_style(UnitStructStyle::Name);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?id=340282366920938463463374607431768211455&offset=-170141183460469231731687303715884105728&point=-1,2&version=Latest");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = maplit::btreemap! { "shape" => Shape::Circle(1, 2, 3) };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?shape=1,2,3");
+        // the elements of a sequence can't be sequences
+        let params = maplit::btreemap! { "shape" => Shape::Segment(Point(0, 0), Point(1, 1)) };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a sequence in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        let params = maplit::btreemap! { Point(1, 2) => 1_i128 };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?1,2=1");
     }
 
     #[test]
@@ -199,6 +277,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_flattened_pieces() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Pagination {
+            offset: Option<String>,
+            limit: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Sort {
+            sort_by: String,
+            descending: Option<String>,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(flatten)]
+            pagination: Pagination,
+            q: Option<String>,
+            #[serde(flatten)]
+            sort: Option<Sort>,
+            #[serde(flatten)]
+            extra: std::collections::BTreeMap<String, String>,
+        }
+
+        let params = Params {
+            pagination: Pagination {
+                offset: None,
+                limit: String::from("20"),
+            },
+            q: None,
+            sort: None,
+            extra: maplit::btreemap! { String::from("tamo le chat") => String::from("kefir") },
+        };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?limit=20&tamo+le+chat=kefir");
+        assert_eq!(from_str::<Params>(&url_params).unwrap(), params);
+
+        let params = Params {
+            pagination: Pagination {
+                offset: Some(String::from("40")),
+                limit: String::from("20"),
+            },
+            q: Some(String::from("doggo")),
+            sort: Some(Sort {
+                sort_by: String::from("age"),
+                descending: None,
+            }),
+            extra: Default::default(),
+        };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?offset=40&limit=20&q=doggo&sort_by=age");
+        assert_eq!(from_str::<Params>(&url_params).unwrap(), params);
+
+        let config = Config::new().compat(Compat::Yaup);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?offset=40&limit=20&q=doggo&sort_by=age&descending=null");
+
+        #[derive(Serialize)]
+        struct Empty {
+            #[serde(flatten)]
+            sort: Option<Sort>,
+        }
+
+        insta::assert_snapshot!(to_string(&Empty { sort: None }).unwrap(), @"?");
+    }
+
     #[test]
     fn test_seq_of_struct() {
         #[derive(Serialize, Debug)]
@@ -303,8 +447,54 @@ mod tests {
     #[test]
     fn test_sequence_as_key() {
         let url_params =
-            to_string(&maplit::hashmap! { vec![1, 2] => vec![1, 2], vec![0] => vec![0] });
-        insta::assert_snapshot!(url_params.unwrap(), @"?1,2=1,2&0=0");
+            to_string(&maplit::btreemap! { vec![1, 2] => vec![1, 2], vec![0] => vec![0] });
+        insta::assert_snapshot!(url_params.unwrap(), @"?0=0&1,2=1,2");
+    }
+
+    #[test]
+    fn test_map() {
+        let params = maplit::btreemap! {
+            "cursor" => Some("42"),
+            "per_page" => None,
+            "tamo le chat" => Some("a&b=c"),
+            "" => Some("empty key"),
+        };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?=empty+key&cursor=42&tamo+le+chat=a%26b%3Dc");
+        let expected = maplit::hashmap! {
+            String::new() => String::from("empty key"),
+            String::from("cursor") => String::from("42"),
+            String::from("tamo le chat") => String::from("a&b=c"),
+        };
+        assert_eq!(
+            from_str::<std::collections::HashMap<String, String>>(&url_params).unwrap(),
+            expected
+        );
+
+        // nothing is written when the first values are omitted
+        let params = maplit::btreemap! { 1 => None, 2 => Some(vec![1, 2]), 3 => Some(Vec::new()) };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?2=1,2");
+
+        let params = maplit::btreemap! { 'a' => 1, '&' => 2, '=' => 3 };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?%26=2&%3D=3&a=1");
+
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize)]
+        enum Field {
+            Name,
+            Age,
+        }
+        let params = maplit::btreemap! { Field::Name => "tamo", Field::Age => "7" };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?Name=tamo&Age=7");
+
+        let params = maplit::btreemap! { (1, "a b") => true };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?1,a+b=true");
+        let config = Config::new().sequence_style(SequenceStyle::Repeated);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?1,a+b=true");
+
+        let params = maplit::btreemap! { Some(()) => 1 };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a unit as a key. Only simple values and sequences of simple values are supported on the left-hand side of a parameter.");
+        let params = maplit::btreemap! { vec![vec![1]] => 1 };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a sequence as a key. Only simple values and sequences of simple values are supported on the left-hand side of a parameter.");
     }
 
     #[test]
@@ -331,4 +521,1604 @@ mod tests {
         let url_params = to_string(&url_params);
         insta::assert_snapshot!(url_params.unwrap(), @"?transparent=0,1,2&hello=true");
     }
+
+    #[test]
+    fn test_from_str() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Filter {
+            New,
+            Registered,
+            Blocked,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            cursor: Option<usize>,
+            per_page: Option<usize>,
+            username: String,
+            filter: Vec<Filter>,
+            tags: Vec<String>,
+            ratio: f32,
+            enabled: bool,
+        }
+
+        let params = Params {
+            cursor: Some(42),
+            per_page: None,
+            username: String::from("tamo & kefir"),
+            filter: vec![Filter::New, Filter::Blocked],
+            tags: vec![String::from("a,b"), String::from("c")],
+            ratio: 3.15,
+            enabled: true,
+        };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?cursor=42&username=tamo+%26+kefir&filter=New,Blocked&tags=a%2Cb,c&ratio=3.15&enabled=true");
+        assert_eq!(from_str::<Params>(&url_params).unwrap(), params);
+
+        // the leading `?` is optional
+        let params: Params = from_str("username=tamo&ratio=1&enabled=false").unwrap();
+        insta::assert_debug_snapshot!(params, @r#"
+        Params {
+            cursor: None,
+            per_page: None,
+            username: "tamo",
+            filter: [],
+            tags: [],
+            ratio: 1.0,
+            enabled: false,
+        }
+        "#);
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Params {
+            num: usize,
+            seq: Option<Vec<usize>>,
+        }
+
+        let error = from_str::<Params>("?seq=1,2").unwrap_err();
+        insta::assert_snapshot!(error, @"missing field `num`");
+        let error = from_str::<Params>("?num=doggo").unwrap_err();
+        insta::assert_snapshot!(error, @r#"invalid value: string "doggo", expected a u64"#);
+        let error = from_str::<usize>("?num=42").unwrap_err();
+        insta::assert_snapshot!(error, @"Tried to deserialize a u64 at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        let error =
+            from_str::<std::collections::HashMap<String, Vec<Vec<usize>>>>("?a=1,2").unwrap_err();
+        insta::assert_snapshot!(error, @"Tried to deserialize a sequence in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let params: std::collections::BTreeMap<String, Vec<u8>> =
+            super::from_bytes(b"?doggo=1,2&catto=3").unwrap();
+        insta::assert_debug_snapshot!(params, @r#"
+        {
+            "catto": [
+                3,
+            ],
+            "doggo": [
+                1,
+                2,
+            ],
+        }
+        "#);
+        assert!(
+            super::from_bytes::<std::collections::BTreeMap<String, String>>(b"?a=\xff").is_err()
+        );
+    }
+
+    #[test]
+    fn test_from_str_borrowed() {
+        use std::borrow::Cow;
+
+        #[derive(Debug, Deserialize)]
+        struct Params<'a> {
+            name: &'a str,
+            #[serde(borrow)]
+            plain: Cow<'a, str>,
+            #[serde(borrow)]
+            escaped: Cow<'a, str>,
+            seq: Vec<&'a str>,
+        }
+
+        let input = String::from("?name=tamo&plain=kefir&escaped=kefir+%26+echo&seq=a,b");
+        let params: Params = from_str(&input).unwrap();
+        assert_eq!(params.name, "tamo");
+        assert!(matches!(params.plain, Cow::Borrowed("kefir")));
+        assert!(matches!(params.escaped, Cow::Owned(ref escaped) if escaped == "kefir & echo"));
+        assert_eq!(params.seq, ["a", "b"]);
+
+        // a `&str` can't be borrowed from an escaped value
+        let error = from_str::<Params>("?name=tamo+kefir").unwrap_err();
+        insta::assert_snapshot!(error, @r#"invalid type: string "tamo kefir", expected a borrowed string"#);
+    }
+
+    #[test]
+    fn test_sequence_style() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            filter: Vec<String>,
+            pair: (usize, bool),
+            empty: Vec<usize>,
+            name: String,
+        }
+
+        let params = Params {
+            filter: vec![String::from("kefir & echo"), String::from("a|b,c")],
+            pair: (42, true),
+            empty: Vec::new(),
+            name: String::from("tamo"),
+        };
+        let with_style = |style| {
+            let config = Config::new().sequence_style(style);
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Params>(&url_params, &config).unwrap(),
+                params
+            );
+            url_params
+        };
+        insta::assert_snapshot!(with_style(SequenceStyle::Comma), @"?filter=kefir+%26+echo,a%7Cb%2Cc&pair=42,true&name=tamo");
+        insta::assert_snapshot!(with_style(SequenceStyle::Repeated), @"?filter=kefir+%26+echo&filter=a%7Cb%2Cc&pair=42&pair=true&name=tamo");
+        insta::assert_snapshot!(with_style(SequenceStyle::Brackets), @"?filter[]=kefir+%26+echo&filter[]=a%7Cb%2Cc&pair[]=42&pair[]=true&name=tamo");
+        insta::assert_snapshot!(with_style(SequenceStyle::Indexed), @"?filter[0]=kefir+%26+echo&filter[1]=a%7Cb%2Cc&pair[0]=42&pair[1]=true&name=tamo");
+        insta::assert_snapshot!(with_style(SequenceStyle::SpaceDelimited), @"?filter=kefir+%26+echo%20a%7Cb%2Cc&pair=42%20true&name=tamo");
+        insta::assert_snapshot!(with_style(SequenceStyle::PipeDelimited), @"?filter=kefir+%26+echo|a%7Cb%2Cc&pair=42|true&name=tamo");
+
+        // the indexes don't need to be in order and the brackets may be encoded
+        let config = Config::new().sequence_style(SequenceStyle::Indexed);
+        let params: Params = from_str_with(
+            "filter%5B1%5D=b&filter[0]=a&pair[0]=1&pair[1]=false&name=tamo",
+            &config,
+        )
+        .unwrap();
+        assert_eq!(params.filter, ["a", "b"]);
+        let config = Config::new().sequence_style(SequenceStyle::Repeated);
+        let error = from_str_with::<Params>("pair=1&pair=true&name=a&name=b", &config).unwrap_err();
+        insta::assert_snapshot!(error, @"invalid length 2, expected a single value");
+    }
+
+    #[test]
+    fn test_escaped_delimiters() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Sign {
+            #[serde(rename = "+")]
+            Plus,
+            #[serde(rename = ",")]
+            Comma,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            chars: Vec<char>,
+            signs: Vec<Sign>,
+            numbers: Vec<f64>,
+            words: Vec<String>,
+        }
+
+        let params = Params {
+            chars: vec![',', '&', ' ', '+'],
+            signs: vec![Sign::Plus, Sign::Comma],
+            numbers: vec![-1.5, 2.0],
+            words: vec![String::from("a b"), String::from("c;d-e")],
+        };
+        let with_config = |config: Config| {
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Params>(&url_params, &config).unwrap(),
+                params
+            );
+            url_params
+        };
+        insta::assert_snapshot!(with_config(Config::new()), @"?chars=%2C,%26,+,%2B&signs=%2B,%2C&numbers=-1.5,2&words=a+b,c%3Bd-e");
+        insta::assert_snapshot!(with_config(Config::new().encoding(Encoding::Custom(",+;-"))), @"?chars=%2C,%26,%20,+&signs=+,%2C&numbers=-1.5,2&words=a%20b,c;d-e");
+        insta::assert_snapshot!(
+            with_config(Config::new().sequence_style(SequenceStyle::Delimited("-;"))),
+            @"?chars=%2C-;%26-;+-;%2B&signs=%2B-;%2C&numbers=%2D1.5-;2&words=a+b-;c%3Bd%2De"
+        );
+        insta::assert_snapshot!(
+            with_config(Config::new().sequence_style(SequenceStyle::Delimited("+"))),
+            @"?chars=%2C+%26+%20+%2B&signs=%2B+%2C&numbers=-1.5+2&words=a%20b+c%3Bd-e"
+        );
+        insta::assert_snapshot!(
+            with_config(Config::new().sequence_style(SequenceStyle::SpaceDelimited).encoding(Encoding::Rfc3986)),
+            @"?chars=%2C%20%26%20+%20%2B&signs=%2B%20%2C&numbers=-1.5%202&words=a+b%20c%3Bd-e"
+        );
+
+        let config = Config::new().sequence_style(SequenceStyle::Delimited("x"));
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `x`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+
+        // a delimiter ending the parameter
+        let config = Config::new().sequence_style(SequenceStyle::Delimited("&"));
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `&`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        let config = Config::new()
+            .sequence_style(SequenceStyle::Delimited(";"))
+            .pair_separator(';');
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `;`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+
+        // a sequence used as a key is escaped the same way
+        let params = maplit::btreemap! { vec![",", "a,b"] => 1 };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?%2C,a%2Cb=1");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_delimited_round_trip(
+            filter in proptest::collection::vec("[a-c ,|;:+%&=.é-]{0,4}", 0..4),
+            letters in proptest::collection::vec(proptest::char::range(' ', '~'), 0..4),
+            style in proptest::sample::select(vec![
+                SequenceStyle::Comma,
+                SequenceStyle::SpaceDelimited,
+                SequenceStyle::PipeDelimited,
+                SequenceStyle::Delimited(";"),
+                SequenceStyle::Delimited("::"),
+                SequenceStyle::Delimited("+-"),
+                SequenceStyle::Delimited("."),
+                SequenceStyle::Delimited("&"),
+                SequenceStyle::Delimited("="),
+                SequenceStyle::Delimited("#"),
+                SequenceStyle::Delimited("!"),
+            ]),
+            encoding in proptest::sample::select(vec![
+                Encoding::Form,
+                Encoding::Rfc3986,
+                Encoding::Custom(",|;:+.-"),
+            ]),
+            separator in proptest::sample::select(vec!['&', '!']),
+            nested in proptest::collection::vec(
+                proptest::collection::vec("[a-c ,|*+%-]{0,3}", 0..3),
+                0..3,
+            ),
+        ) {
+            #[derive(Debug, PartialEq, Serialize, Deserialize)]
+            struct Params {
+                filter: Vec<String>,
+                letters: Vec<char>,
+                nested: Vec<Vec<String>>,
+            }
+
+            let params = Params { filter, letters, nested };
+            let config = Config::new()
+                .sequence_style(style)
+                .encoding(encoding)
+                .pair_separator(separator)
+                .nested_delimiters(&["*"]);
+            // the delimiters ending a parameter are rejected once an element is written
+            let rejected = style
+                .delimiter()
+                .is_some_and(|delimiter| delimiter.contains(['&', '=', '#', separator]));
+            let empty = params.filter.is_empty() && params.letters.is_empty() && params.nested.is_empty();
+            // a nested sequence holding a single empty element can't be told apart from an empty one
+            let ambiguous = params.nested.iter().any(|elements| elements[..] == [""]);
+            match to_string_with(&params, &config) {
+                Ok(url_params) => {
+                    proptest::prop_assert!(!rejected || empty);
+                    proptest::prop_assert!(!ambiguous);
+                    proptest::prop_assert_eq!(from_str_with::<Params>(&url_params, &config).unwrap(), params);
+                }
+                Err(error) => proptest::prop_assert!(rejected || ambiguous, "{}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            query: String,
+            filter: Vec<String>,
+            page: Option<usize>,
+            name: String,
+        }
+
+        let params = Params {
+            query: String::new(),
+            filter: Vec::new(),
+            page: None,
+            name: String::from("kefir & echo"),
+        };
+        let round_trip = |config: Config| {
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Params>(&url_params, &config).unwrap(),
+                params
+            );
+            url_params
+        };
+        insta::assert_snapshot!(round_trip(Config::new()), @"?name=kefir+%26+echo");
+        insta::assert_snapshot!(
+            round_trip(Config::new().prefix("").pair_separator(';').key_value_separator(':')),
+            @"name:kefir+%26+echo"
+        );
+        insta::assert_snapshot!(
+            round_trip(Config::new().none_policy(OmitPolicy::Null).empty_policy(OmitPolicy::Empty)),
+            @"?query=&filter=&page=null&name=kefir+%26+echo"
+        );
+        insta::assert_snapshot!(
+            round_trip(Config::new().none_policy(OmitPolicy::Empty).encoding(Encoding::Rfc3986)),
+            @"?page=&name=kefir%20%26%20echo"
+        );
+        insta::assert_snapshot!(
+            round_trip(Config::new().sequence_style(SequenceStyle::Delimited(";"))),
+            @"?name=kefir+%26+echo"
+        );
+
+        let config = Config::new().sequence_style(SequenceStyle::Delimited(";"));
+        let params: Params = from_str_with("?filter=a;b&name=c", &config).unwrap();
+        assert_eq!(params.filter, ["a", "b"]);
+        // with RFC 3986 a `+` is not a space
+        let config = Config::new().encoding(Encoding::Rfc3986);
+        let params: Params = from_str_with("?name=a+b%20c", &config).unwrap();
+        assert_eq!(params.name, "a+b c");
+        // a separator written as-is inside the values
+        let params = Params {
+            query: String::from("x-b=y"),
+            filter: Vec::new(),
+            page: Some(1),
+            name: String::from("z"),
+        };
+        let config = Config::new().pair_separator('-');
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to separate the parameters with `-`. Only separators the encoding never writes as-is, other than `[`, `]`, `%`, `+`, a space, or `:` with the prefixed or indexed variants, and different from each other, are supported.");
+        let config = Config::new().key_value_separator('&');
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to separate the parameters with `&`. Only separators the encoding never writes as-is, other than `[`, `]`, `%`, `+`, a space, or `:` with the prefixed or indexed variants, and different from each other, are supported.");
+    }
+
+    #[test]
+    fn test_encoding() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(rename = "a b&c")]
+            renamed: String,
+            filter: String,
+            path: Option<String>,
+        }
+
+        let params = Params {
+            renamed: String::from("d=e"),
+            filter: String::from("genres:comedy,drama AND rating>=4"),
+            path: Some(String::from("/dog+cat/")),
+        };
+        let round_trip = |config: Config| {
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Params>(&url_params, &config).unwrap(),
+                params
+            );
+            url_params
+        };
+        insta::assert_snapshot!(round_trip(Config::new()), @"?a+b%26c=d%3De&filter=genres%3Acomedy%2Cdrama+AND+rating%3E%3D4&path=%2Fdog%2Bcat%2F");
+        insta::assert_snapshot!(round_trip(Config::new().encoding(Encoding::Rfc3986)), @"?a%20b%26c=d%3De&filter=genres%3Acomedy%2Cdrama%20AND%20rating%3E%3D4&path=%2Fdog%2Bcat%2F");
+        insta::assert_snapshot!(round_trip(Config::new().encoding(Encoding::Custom(",:/"))), @"?a%20b%26c=d%3De&filter=genres:comedy,drama%20AND%20rating%3E%3D4&path=/dog%2Bcat/");
+
+        // the safe characters can't end a parameter or be left undecoded
+        let config = Config::new().encoding(Encoding::Custom("&="));
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to keep `&` as-is in the encoding. Only ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters can be kept.");
+        let config = Config::new().encoding(Encoding::Custom("é"));
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to keep `é` as-is in the encoding. Only ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters can be kept.");
+
+        // yaup writes the field names as-is
+        let config = Config::new().compat(Compat::Yaup);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?a b&c=d%3De&filter=genres%3Acomedy%2Cdrama+AND+rating%3E%3D4&path=%2Fdog%2Bcat%2F");
+    }
+
+    #[test]
+    fn test_serializer_with_config() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            doggo: &'static str,
+        }
+
+        let mut serializer = super::Serializer::with_config(Vec::new(), Config::new().prefix("&"));
+        Params { doggo: "kefir" }
+            .serialize(&mut serializer)
+            .unwrap();
+        insta::assert_snapshot!(String::from_utf8(serializer.into_inner()).unwrap(), @"&doggo=kefir");
+    }
+
+    #[test]
+    fn test_omit_policy() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            q: String,
+            filter: Vec<String>,
+            page: Option<usize>,
+            unit: (),
+        }
+
+        let params = Params {
+            q: String::new(),
+            filter: Vec::new(),
+            page: None,
+            unit: (),
+        };
+        let round_trip = |config: Config| {
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Params>(&url_params, &config).unwrap(),
+                params
+            );
+            url_params
+        };
+        insta::assert_snapshot!(round_trip(Config::new()), @"?");
+        insta::assert_snapshot!(round_trip(Config::new().empty_str_policy(OmitPolicy::Empty)), @"?q=");
+        insta::assert_snapshot!(round_trip(Config::new().empty_str_policy(OmitPolicy::Null)), @"?q=null");
+        insta::assert_snapshot!(round_trip(Config::new().empty_seq_policy(OmitPolicy::Empty)), @"?filter=");
+        insta::assert_snapshot!(round_trip(Config::new().empty_seq_policy(OmitPolicy::Null)), @"?filter=null");
+        insta::assert_snapshot!(round_trip(Config::new().none_policy(OmitPolicy::Empty)), @"?page=");
+        insta::assert_snapshot!(round_trip(Config::new().none_policy(OmitPolicy::Null)), @"?page=null");
+        insta::assert_snapshot!(round_trip(Config::new().unit_policy(OmitPolicy::Empty)), @"?unit=");
+        insta::assert_snapshot!(round_trip(Config::new().unit_policy(OmitPolicy::Null)), @"?unit=null");
+
+        // inside a sequence the policies decide what the element looks like
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Pages {
+            page: Vec<Option<usize>>,
+        }
+        let pages = Pages {
+            page: vec![Some(1), None, Some(3)],
+        };
+        let url_params = to_string_with(&pages, &Config::new().none_policy(OmitPolicy::Null));
+        insta::assert_snapshot!(url_params.unwrap(), @"?page=1,null,3");
+        let url_params = to_string(&pages).unwrap();
+        insta::assert_snapshot!(url_params, @"?page=1,,3");
+        assert_eq!(from_str::<Pages>(&url_params).unwrap(), pages);
+
+        // the empty elements keep their position in every style
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Elements {
+            page: Vec<Option<usize>>,
+            word: Vec<String>,
+        }
+        let elements = Elements {
+            page: vec![Some(1), None, Some(3)],
+            word: vec![String::new(), String::from("a")],
+        };
+        let with_style = |style| {
+            let config = Config::new().sequence_style(style);
+            let url_params = to_string_with(&elements, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Elements>(&url_params, &config).unwrap(),
+                elements
+            );
+            url_params
+        };
+        insta::assert_snapshot!(with_style(SequenceStyle::Comma), @"?page=1,,3&word=,a");
+        insta::assert_snapshot!(with_style(SequenceStyle::Repeated), @"?page=1&page=&page=3&word=&word=a");
+        insta::assert_snapshot!(with_style(SequenceStyle::Brackets), @"?page[]=1&page[]=&page[]=3&word[]=&word[]=a");
+        insta::assert_snapshot!(with_style(SequenceStyle::Indexed), @"?page[0]=1&page[1]=&page[2]=3&word[0]=&word[1]=a");
+
+        // a single empty element is told apart from an empty sequence left out
+        let params = maplit::btreemap! { "filter" => vec![""] };
+        let url_params = to_string(&params).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=");
+        assert_eq!(
+            from_str::<std::collections::BTreeMap<String, Vec<String>>>(&url_params).unwrap()
+                ["filter"],
+            params["filter"]
+        );
+        let config = Config::new().empty_seq_policy(OmitPolicy::Empty);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence holding a single empty element in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        // but not from one written after the prefix of its variant
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Filter {
+            Tags(Vec<String>),
+        }
+        let config = Config::new().enum_style(EnumStyle::Prefixed);
+        let params = maplit::btreemap! { "filter" => Filter::Tags(vec![]) };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=Tags:");
+        assert_eq!(
+            from_str_with::<std::collections::BTreeMap<String, Filter>>(&url_params, &config)
+                .unwrap()["filter"],
+            params["filter"]
+        );
+        let params = maplit::btreemap! { "filter" => Filter::Tags(vec![String::new()]) };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence holding a single empty element in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    /// Serialize the value with both the default mode and the yaup compatible mode, and check
+    /// the latter against yaup itself.
+    fn differential<T: Serialize>(value: &T) -> (String, String) {
+        let config = Config::new().compat(Compat::Yaup);
+        let nyaup = to_string(value).unwrap_or_else(|e| format!("error: {e}"));
+        let compat = to_string_with(value, &config).unwrap_or_else(|e| format!("error: {e}"));
+        let yaup = yaup::to_string(value).unwrap_or_else(|e| format!("error: {e}"));
+        assert_eq!(compat.starts_with("error"), yaup.starts_with("error"));
+        if !yaup.starts_with("error") {
+            assert_eq!(compat, yaup);
+        }
+        (nyaup, compat)
+    }
+
+    #[test]
+    fn test_yaup_compat() {
+        #[derive(Serialize)]
+        enum Filter {
+            New,
+            Registered,
+            Blocked,
+        }
+
+        #[derive(Serialize)]
+        struct Params {
+            cursor: Option<usize>,
+            per_page: Option<usize>,
+            username: String,
+            doggo: String,
+            filter: Vec<Filter>,
+            catto: Vec<usize>,
+            unit: (),
+        }
+
+        let params = Params {
+            cursor: Some(42),
+            per_page: None,
+            username: String::from("tamo le chat"),
+            doggo: String::new(),
+            filter: vec![Filter::New, Filter::Registered, Filter::Blocked],
+            catto: Vec::new(),
+            unit: (),
+        };
+        let (nyaup, yaup) = differential(&params);
+        insta::assert_snapshot!(nyaup, @"?cursor=42&username=tamo+le+chat&filter=New,Registered,Blocked");
+        insta::assert_snapshot!(yaup, @"?cursor=42&per_page=null&username=tamo+le+chat&doggo=&filter=New,Registered,Blocked&catto=&unit=");
+
+        #[derive(Serialize)]
+        struct Elements {
+            pages: Vec<Option<usize>>,
+            names: Vec<&'static str>,
+            maybe: Option<Vec<usize>>,
+        }
+
+        let elements = Elements {
+            pages: vec![None, Some(2), None],
+            names: vec!["a", "", "c d"],
+            maybe: Some(Vec::new()),
+        };
+        let (nyaup, yaup) = differential(&elements);
+        insta::assert_snapshot!(nyaup, @"?pages=,2,&names=a,,c+d");
+        insta::assert_snapshot!(yaup, @"?pages=null,2,null&names=a,,c+d&maybe=");
+
+        #[derive(Serialize)]
+        struct Empty {
+            cursor: Option<usize>,
+        }
+
+        let (nyaup, yaup) = differential(&Empty { cursor: None });
+        insta::assert_snapshot!(nyaup, @"?");
+        insta::assert_snapshot!(yaup, @"?cursor=null");
+
+        let (nyaup, yaup) = differential(&Some(Empty { cursor: Some(1) }));
+        insta::assert_snapshot!(nyaup, @"?cursor=1");
+        insta::assert_snapshot!(yaup, @"?cursor=1");
+
+        let params = maplit::btreemap! {
+            vec![1, 2] => Some(vec![1, 2]),
+            vec![3] => None,
+            vec![] => Some(Vec::new()),
+        };
+        let (nyaup, yaup) = differential(&params);
+        insta::assert_snapshot!(nyaup, @"?1,2=1,2");
+        insta::assert_snapshot!(yaup, @"?=&1,2=1,2&3=null");
+
+        let params = maplit::btreemap! { "tamo le chat" => "a&b", "doggo" => "" };
+        let (nyaup, yaup) = differential(&params);
+        insta::assert_snapshot!(nyaup, @"?tamo+le+chat=a%26b");
+        insta::assert_snapshot!(yaup, @"?doggo=&tamo+le+chat=a%26b");
+
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "a b")]
+            renamed: char,
+        }
+
+        let (nyaup, yaup) = differential(&Renamed { renamed: 'c' });
+        insta::assert_snapshot!(nyaup, @"?a+b=c");
+        insta::assert_snapshot!(yaup, @"?a b=c");
+
+        let (nyaup, yaup) = differential(&());
+        insta::assert_snapshot!(nyaup, @"");
+        insta::assert_snapshot!(yaup, @"");
+
+        #[derive(Serialize)]
+        struct Nested {
+            params: Empty,
+        }
+
+        let (nyaup, yaup) = differential(&Nested {
+            params: Empty { cursor: None },
+        });
+        insta::assert_snapshot!(nyaup, @"error: Tried to serialize a struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        insta::assert_snapshot!(yaup, @"error: Tried to serialize a struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    #[test]
+    fn test_nesting_style() {
+        #[derive(Serialize)]
+        struct Range {
+            gte: Option<usize>,
+            lt: Option<usize>,
+        }
+
+        #[derive(Serialize)]
+        struct Filter {
+            name: &'static str,
+            tags: Vec<&'static str>,
+            age: Range,
+        }
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            filter: Filter,
+            extra: Option<std::collections::BTreeMap<&'static str, &'static str>>,
+        }
+
+        let params = Params {
+            q: "doggo",
+            filter: Filter {
+                name: "kefir le chien",
+                tags: vec!["good", "boy"],
+                age: Range {
+                    gte: Some(3),
+                    lt: None,
+                },
+            },
+            extra: Some(maplit::btreemap! { "a b" => "c", "d" => "" }),
+        };
+
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        let config = Config::new().nesting_style(NestingStyle::Brackets);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?q=doggo&filter[name]=kefir+le+chien&filter[tags]=good,boy&filter[age][gte]=3&extra[a+b]=c");
+        let config = config.sequence_style(SequenceStyle::Brackets);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?q=doggo&filter[name]=kefir+le+chien&filter[tags][]=good&filter[tags][]=boy&filter[age][gte]=3&extra[a+b]=c");
+
+        let config = Config::new().nesting_style(NestingStyle::DeepObject);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        let params = maplit::btreemap! { "filter" => maplit::btreemap! { "name" => "kefir", "breed" => "" } };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?filter[name]=kefir");
+
+        let config = Config::new().nesting_style(NestingStyle::Dotted);
+        let params = Params {
+            q: "doggo",
+            filter: Filter {
+                name: "kefir",
+                tags: vec!["good", "boy"],
+                age: Range {
+                    gte: Some(3),
+                    lt: Some(7),
+                },
+            },
+            extra: Some(maplit::btreemap! { "v1.2" => "c" }),
+        };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?q=doggo&filter.name=kefir&filter.tags=good,boy&filter.age.gte=3&filter.age.lt=7&extra.v1%2E2=c");
+        let params =
+            maplit::btreemap! { "filter.v2" => maplit::btreemap! { "name.first" => "kefir" } };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?filter%2Ev2.name%2Efirst=kefir");
+
+        // the elements of a sequence can't be nested
+        let config = Config::new().nesting_style(NestingStyle::Brackets);
+        let params =
+            maplit::btreemap! { "filter" => vec![maplit::btreemap! { "name" => "kefir" }] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a map in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    #[test]
+    fn test_enum_style() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Filter {
+            ById(u32),
+            Tags(Vec<String>),
+            Range(u32, u32),
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Sort {
+            Asc,
+            Desc,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            filter: Filter,
+            sort: Sort,
+            sorts: Vec<Sort>,
+        }
+
+        let params = Params {
+            filter: Filter::ById(3),
+            sort: Sort::Desc,
+            sorts: vec![Sort::Asc, Sort::Desc],
+        };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?filter=3&sort=Desc&sorts=Asc,Desc");
+
+        let config = Config::new().enum_style(EnumStyle::Index);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=0:3&sort=1&sorts=0,1");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().enum_style(EnumStyle::Prefixed);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=ById:3&sort=Desc&sorts=Asc,Desc");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().enum_style(EnumStyle::TagParam("_type"));
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=3&filter_type=ById&sort=Desc&sorts=Asc,Desc");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = Params {
+            filter: Filter::Range(1, 10),
+            sort: Sort::Asc,
+            sorts: vec![],
+        };
+        let config = Config::new()
+            .enum_style(EnumStyle::Prefixed)
+            .variant_case(VariantCase::Snake);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=range:1,10&sort=asc");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().enum_style(EnumStyle::Index);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=2:1,10&sort=0");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .enum_style(EnumStyle::TagParam("_type"))
+            .variant_case(VariantCase::Kebab);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=1,10&filter_type=range&sort=asc");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = Params {
+            filter: Filter::Tags(vec![String::from("good"), String::from("boy")]),
+            sort: Sort::Asc,
+            sorts: vec![Sort::Desc],
+        };
+        let config = Config::new()
+            .enum_style(EnumStyle::Prefixed)
+            .variant_case(VariantCase::Camel);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?filter=tags:good,boy&sort=asc&sorts=desc");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+        // the prefix can't be shared by the parameters of the elements
+        let config = config.sequence_style(SequenceStyle::Repeated);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        // an omitted `None` would leave the prefix alone, read back as an empty value
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Page {
+            At(Option<u32>),
+        }
+        let params = maplit::btreemap! { String::from("page") => Page::At(None) };
+        let config = Config::new().enum_style(EnumStyle::Prefixed);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a prefixed variant holding an omitted none in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        for policy in [OmitPolicy::Empty, OmitPolicy::Null] {
+            let config = config.clone().none_policy(policy);
+            let url_params = to_string_with(&params, &config).unwrap();
+            assert_eq!(
+                from_str_with::<std::collections::BTreeMap<String, Page>>(&url_params, &config)
+                    .unwrap(),
+                params
+            );
+        }
+
+        // the tag of an element would have nowhere to go
+        let config = Config::new().enum_style(EnumStyle::TagParam("_type"));
+        let params = maplit::btreemap! { "filters" => vec![Filter::ById(1)] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a tagged variant in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    #[test]
+    fn test_variant_tag() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct ById {
+            id: u32,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Search {
+            ByName { name: String, exact: bool },
+            ById(ById),
+            Everything,
+            Nothing(()),
+            Pair(u32, u32),
+        }
+
+        let search = Search::ByName {
+            name: String::from("kefir"),
+            exact: true,
+        };
+        // the variant is dropped by default
+        insta::assert_snapshot!(to_string(&search).unwrap(), @"?name=kefir&exact=true");
+        insta::assert_snapshot!(to_string(&Search::Everything).unwrap_err(), @"Tried to serialize a unit variant at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(from_str::<Search>("?name=kefir&exact=true").unwrap_err(), @"Tried to deserialize a enum at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+
+        let config = Config::new().variant_tag("type");
+        for search in [
+            search,
+            Search::ById(ById { id: 3 }),
+            Search::Everything,
+            Search::Nothing(()),
+        ] {
+            let url_params = to_string_with(&search, &config).unwrap();
+            assert_eq!(
+                from_str_with::<Search>(&url_params, &config).unwrap(),
+                search
+            );
+        }
+        insta::assert_snapshot!(to_string_with(&Search::ById(ById { id: 3 }), &config).unwrap(), @"?type=ById&id=3");
+        insta::assert_snapshot!(to_string_with(&Search::Everything, &config).unwrap(), @"?type=Everything");
+        insta::assert_snapshot!(to_string_with(&Search::Pair(1, 2), &config).unwrap_err(), @"Tried to serialize a tuple variant at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(from_str_with::<Search>("?name=kefir&exact=true", &config).unwrap_err(), @"missing field `type`");
+        insta::assert_snapshot!(from_str_with::<Search>("?type=Pair", &config).unwrap_err(), @"Tried to deserialize a tuple variant at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+
+        // a key named after the tag would be read as the tag
+        #[derive(Serialize)]
+        enum Kind {
+            A { r#type: &'static str },
+            B(std::collections::BTreeMap<&'static str, u32>),
+        }
+        insta::assert_snapshot!(to_string_with(&Kind::A { r#type: "z" }, &config).unwrap_err(), @"Tried to serialize a parameter named `type` along with the variant tag. The keys of a tagged variant can't be its tag.");
+        let kind = Kind::B(maplit::btreemap! { "type" => 1 });
+        insta::assert_snapshot!(to_string_with(&kind, &config).unwrap_err(), @"Tried to serialize a parameter named `type` along with the variant tag. The keys of a tagged variant can't be its tag.");
+
+        let config = config
+            .variant_tag("search type")
+            .variant_case(VariantCase::Snake);
+        let url_params = to_string_with(&Search::ById(ById { id: 3 }), &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?search+type=by_id&id=3");
+        assert_eq!(
+            from_str_with::<Search>(&url_params, &config).unwrap(),
+            Search::ById(ById { id: 3 })
+        );
+
+        let config = config.enum_style(EnumStyle::Index);
+        let url_params = to_string_with(&Search::Everything, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?search+type=2");
+        assert_eq!(
+            from_str_with::<Search>(&url_params, &config).unwrap(),
+            Search::Everything
+        );
+    }
+
+    #[test]
+    fn test_nullable() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Patch {
+            name: Option<Option<String>>,
+            age: Option<Option<u8>>,
+            breed: Nullable<String>,
+            tags: Nullable<Vec<String>>,
+            owner: Option<String>,
+        }
+
+        let patch = Patch {
+            name: Some(Some(String::from("kefir"))),
+            age: Some(None),
+            breed: Nullable::Null,
+            tags: Nullable::Absent,
+            owner: None,
+        };
+        let url_params = to_string(&patch).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=kefir&age=null&breed=null");
+        assert_eq!(from_str::<Patch>(&url_params).unwrap(), patch);
+
+        let patch = Patch {
+            name: None,
+            age: Some(Some(3)),
+            breed: Nullable::Value(String::from("null")),
+            tags: Nullable::Value(vec![String::from("good"), String::from("boy")]),
+            owner: Some(String::from("tamo")),
+        };
+        let url_params = to_string(&patch).unwrap();
+        insta::assert_snapshot!(url_params, @"?age=3&breed=null&tags=good,boy&owner=tamo");
+        let read = from_str::<Patch>(&url_params).unwrap();
+        // a value equal to the null token can't be told apart from a null
+        assert_eq!(read.breed, Nullable::Null);
+        assert_eq!(read.tags, patch.tags);
+
+        let config = Config::new().null_token("nil");
+        let patch = Patch {
+            name: Some(None),
+            age: None,
+            breed: Nullable::Value(String::from("null")),
+            tags: Nullable::Null,
+            owner: None,
+        };
+        let url_params = to_string_with(&patch, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=nil&breed=null&tags=nil");
+        assert_eq!(from_str_with::<Patch>(&url_params, &config).unwrap(), patch);
+
+        let config = config.none_policy(OmitPolicy::Null);
+        insta::assert_snapshot!(to_string_with(&patch, &config).unwrap(), @"?name=nil&age=nil&breed=null&tags=nil&owner=nil");
+
+        // an empty value is sent whatever the policy, unlike an empty `Option`
+        let patch = Patch {
+            name: Some(Some(String::new())),
+            age: None,
+            breed: Nullable::Value(String::new()),
+            tags: Nullable::Absent,
+            owner: Some(String::new()),
+        };
+        let url_params = to_string(&patch).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=&breed=");
+        let read = from_str::<Patch>(&url_params).unwrap();
+        assert_eq!(read.name, patch.name);
+        assert_eq!(read.breed, patch.breed);
+        assert_eq!(read.owner, None);
+        let config = Config::new().empty_str_policy(OmitPolicy::Null);
+        insta::assert_snapshot!(to_string_with(&patch, &config).unwrap(), @"?name=&breed=&owner=null");
+    }
+
+    #[test]
+    fn test_raw() {
+        #[derive(Serialize)]
+        struct Params {
+            token: Raw<&'static str>,
+            cursors: Vec<Raw<String>>,
+            q: &'static str,
+        }
+
+        let params = Params {
+            token: Raw("a%2Bb%20c"),
+            cursors: vec![Raw(String::from("x%3D")), Raw(String::from("y,z"))],
+            q: "a+b c",
+        };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?token=a%2Bb%20c&cursors=x%3D,y%2Cz&q=a%2Bb+c");
+        let config = Config::new().sequence_style(SequenceStyle::Repeated);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?token=a%2Bb%20c&cursors=x%3D&cursors=y,z&q=a%2Bb+c");
+
+        let params = maplit::btreemap! { Raw("filter%5Bname%5D") => "kefir" };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?filter%5Bname%5D=kefir");
+
+        let params = maplit::btreemap! { "token" => Raw("a&b") };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to write a raw value containing `&`. Raw values can't contain `&`, `=`, `#` or the separators of the parameters.");
+        let params = maplit::btreemap! { "token" => Raw("a=b") };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to write a raw value containing `=`. Raw values can't contain `&`, `=`, `#` or the separators of the parameters.");
+        let params = maplit::btreemap! { "token" => Raw("a#b") };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to write a raw value containing `#`. Raw values can't contain `&`, `=`, `#` or the separators of the parameters.");
+        let config = Config::new().pair_separator(';');
+        let params = maplit::btreemap! { "token" => Raw("a;b") };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to write a raw value containing `;`. Raw values can't contain `&`, `=`, `#` or the separators of the parameters.");
+    }
+
+    #[test]
+    fn test_bytes_encoding() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(with = "serde_bytes")]
+            cursor: Vec<u8>,
+            #[serde(with = "serde_bytes")]
+            empty: Vec<u8>,
+            digests: Vec<serde_bytes::ByteBuf>,
+        }
+
+        let params = Params {
+            cursor: b"hi\xff".to_vec(),
+            empty: Vec::new(),
+            digests: vec![
+                serde_bytes::ByteBuf::from(vec![0xfb, 0xff]),
+                serde_bytes::ByteBuf::from(b"?".to_vec()),
+            ],
+        };
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a bytes in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        let config = Config::new().bytes_encoding(BytesEncoding::Base64Url);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?cursor=aGn_&digests=-_8%3D,Pw%3D%3D");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().bytes_encoding(BytesEncoding::Base64UrlUnpadded);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?cursor=aGn_&digests=-_8,Pw");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().bytes_encoding(BytesEncoding::Hex);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?cursor=6869ff&digests=fbff,3f");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+        insta::assert_snapshot!(from_str_with::<Params>("?cursor=abc", &config).unwrap_err(), @"invalid value: string \"abc\", expected encoded bytes");
+
+        let config = Config::new()
+            .bytes_encoding(BytesEncoding::Percent)
+            .encoding(Encoding::Rfc3986)
+            .sequence_style(SequenceStyle::Repeated);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?cursor=hi%FF&digests=%FB%FF&digests=%3F");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = maplit::btreemap! { serde_bytes::ByteBuf::from(b"key".to_vec()) => 1 };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?key=1");
+    }
+
+    #[test]
+    fn test_float_and_bool_format() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            ratio: f32,
+            score: f64,
+            bounds: Vec<f64>,
+            good: bool,
+            hungry: bool,
+        }
+
+        let params = Params {
+            ratio: 0.1,
+            score: 1.0 / 3.0,
+            bounds: vec![f64::NEG_INFINITY, 2.5, f64::NAN],
+            good: true,
+            hungry: false,
+        };
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?ratio=0.1&score=0.3333333333333333&bounds=-inf,2.5,NaN&good=true&hungry=false");
+
+        let config = Config::new()
+            .float_format(FloatFormat::Precision(2))
+            .bool_style(BoolStyle::OneZero);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?ratio=0.10&score=0.33&bounds=-inf,2.50,NaN&good=1&hungry=0");
+
+        let config = Config::new().non_finite_floats(NonFiniteFloats::Error);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize `-inf`. Only finite floats are supported unless tokens are configured for the others.");
+
+        let config = Config::new()
+            .non_finite_floats(NonFiniteFloats::Tokens {
+                nan: "nan",
+                inf: "+inf",
+                neg_inf: "-inf",
+            })
+            .bool_style(BoolStyle::YesNo);
+        let params = Params {
+            ratio: 0.1,
+            score: f64::INFINITY,
+            bounds: vec![f64::NEG_INFINITY, 2.5],
+            good: true,
+            hungry: false,
+        };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?ratio=0.1&score=%2Binf&bounds=-inf,2.5&good=yes&hungry=no");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+        let read =
+            from_str_with::<Params>("?ratio=nan&score=0&good=yes&hungry=no", &config).unwrap();
+        assert!(read.ratio.is_nan());
+
+        let config = Config::new().bool_style(BoolStyle::Checkbox);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?ratio=0.1&score=inf&bounds=-inf,2.5&good=on");
+        let read = from_str_with::<Params>("?ratio=0.1&score=1&good=on", &config).unwrap();
+        assert!(read.good && !read.hungry);
+        insta::assert_snapshot!(from_str_with::<Params>("?ratio=0.1&score=1&good=true", &config).unwrap_err(), @"invalid value: string \"true\", expected a boolean");
+        let params = maplit::btreemap! { "good" => vec![true, false, true] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?good=on,,on");
+
+        // the large and tiny floats are written with an exponent
+        let config = Config::new().float_format(FloatFormat::Exponent);
+        let params = maplit::btreemap! { "bounds" => vec![1e300, -2.5e-7, 1e15, 100.0] };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?bounds=1e300,-2.5e-7,1000000000000000,100");
+        assert_eq!(
+            from_str_with::<std::collections::BTreeMap<&str, Vec<f64>>>(&url_params, &config)
+                .unwrap(),
+            params
+        );
+        let params = maplit::btreemap! { "ratio" => f32::MIN_POSITIVE };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?ratio=1.1754944e-38");
+        // the default keeps every digit
+        insta::assert_snapshot!(to_string(&maplit::btreemap! { "ratio" => 1e-7 }).unwrap(), @"?ratio=0.0000001");
+    }
+
+    #[test]
+    fn test_nested_delimiters() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(rename = "_geoBoundingBox")]
+            geo_bounding_box: Vec<(f64, f64)>,
+            paths: Vec<Vec<Vec<String>>>,
+        }
+
+        let params = Params {
+            geo_bounding_box: vec![(1.0, 2.0), (3.0, 4.0)],
+            paths: vec![
+                vec![
+                    vec![String::from("a,b"), String::from("c|d")],
+                    vec![String::from("e;f g")],
+                ],
+                vec![],
+            ],
+        };
+
+        let config = Config::new().nested_delimiters(&["|", ";"]);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?_geoBoundingBox=1|2,3|4&paths=a%2Cb;c%7Cd|e%3Bf+g,");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .sequence_style(SequenceStyle::PipeDelimited)
+            .nested_delimiters(&[",", ";"]);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?_geoBoundingBox=1,2|3,4&paths=a%2Cb;c%7Cd,e%3Bf+g|");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .sequence_style(SequenceStyle::Repeated)
+            .nested_delimiters(&[",", ";"]);
+        let params = Params {
+            geo_bounding_box: vec![(1.0, 2.0), (3.0, 4.0)],
+            paths: vec![vec![vec![String::from("a,b")], vec![String::from("c")]]],
+        };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?_geoBoundingBox=1,2&_geoBoundingBox=3,4&paths=a%2Cb,c");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .sequence_style(SequenceStyle::SpaceDelimited)
+            .nested_delimiters(&[","]);
+        let params = maplit::btreemap! { "pairs" => vec![vec!["a b", "c+d"], vec!["e"]] };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?pairs=a+b,c%2Bd%20e");
+        assert_eq!(
+            from_str_with::<std::collections::BTreeMap<String, Vec<Vec<String>>>>(
+                &url_params,
+                &config
+            )
+            .unwrap()["pairs"],
+            vec![vec!["a b", "c+d"], vec!["e"]]
+        );
+
+        // deeper than the nested delimiters
+        let config = Config::new().nested_delimiters(&["|"]);
+        let params = maplit::btreemap! { "a" => vec![vec![vec![1]]] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        insta::assert_snapshot!(from_str_with::<std::collections::BTreeMap<String, Vec<Vec<Vec<u32>>>>>("?a=1", &config).unwrap_err(), @"Tried to deserialize a sequence in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        // a nested delimiter sharing a character with the one holding it
+        let config = Config::new().nested_delimiters(&[";,"]);
+        let params = maplit::btreemap! { "a" => vec![vec![1]] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `;,`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        let config = Config::new()
+            .sequence_style(SequenceStyle::SpaceDelimited)
+            .nested_delimiters(&["+"]);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `+`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        // a nested delimiter ending the parameter, or splitting the inline pairs
+        let config = Config::new().nested_delimiters(&["&"]);
+        let params = maplit::btreemap! { "m" => vec![vec![1], vec![2, 3]] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `&`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        let config = Config::new().nested_delimiters(&[":"]).inline_pairs(":");
+        let params = maplit::btreemap! { "m" => vec![(vec![1, 2], 3)] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `:`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+
+        // a single empty element can't be told apart from an empty nested sequence
+        let config = Config::new().nested_delimiters(&["|"]);
+        let params = maplit::btreemap! { "paths" => vec![vec!["a"], vec![]] };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?paths=a,");
+        assert_eq!(
+            from_str_with::<std::collections::BTreeMap<String, Vec<Vec<String>>>>(
+                &url_params,
+                &config
+            )
+            .unwrap()["paths"],
+            params["paths"]
+        );
+        let params = maplit::btreemap! { "paths" => vec![vec!["a"], vec![""]] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence holding a single empty element in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+    }
+
+    #[test]
+    fn test_struct_sequence_style() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Item {
+            id: usize,
+            qty: Option<usize>,
+            label: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            items: Vec<Item>,
+            tags: Vec<String>,
+        }
+
+        let params = Params {
+            items: vec![
+                Item {
+                    id: 1,
+                    qty: Some(3),
+                    label: String::from("a,b"),
+                },
+                Item {
+                    id: 2,
+                    qty: None,
+                    label: String::from("c d"),
+                },
+            ],
+            tags: vec![String::from("x"), String::from("y")],
+        };
+
+        let config = Config::new().struct_sequence_style(StructSequenceStyle::Indexed);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?items[0][id]=1&items[0][qty]=3&items[0][label]=a%2Cb&items[1][id]=2&items[1][label]=c+d&tags=x,y");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new().struct_sequence_style(StructSequenceStyle::Columns);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?items.id=1,2&items.qty=3,&items.label=a%2Cb,c+d&tags=x,y");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .sequence_style(SequenceStyle::Brackets)
+            .struct_sequence_style(StructSequenceStyle::Columns);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?items.id[]=1&items.id[]=2&items.qty[]=3&items.qty[]=&items.label[]=a%2Cb&items.label[]=c+d&tags[]=x&tags[]=y");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = Params {
+            items: Vec::new(),
+            tags: Vec::new(),
+        };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        // a struct among the values of a sequence
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Element {
+            Item(Item),
+            Id(usize),
+        }
+        let params = maplit::btreemap! { "items" => vec![Element::Id(1), Element::Item(Item { id: 2, qty: None, label: String::new() })] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence mixing structs and values in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        // the elements that couldn't be read back
+        let params = maplit::btreemap! { "items" => vec![maplit::btreemap! { "a" => 1 }, maplit::btreemap! { "b" => 2 }] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a sequence of structs with differing fields in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+        #[derive(Serialize)]
+        struct Tags {
+            tags: Vec<u32>,
+        }
+        let params = maplit::btreemap! { "items" => vec![Tags { tags: vec![1, 2] }, Tags { tags: Vec::new() }] };
+        let config = Config::new().struct_sequence_style(StructSequenceStyle::Indexed);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to serialize a struct element with every field omitted in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        // each element is serialized once
+        struct Counted<'a>(&'a std::cell::Cell<usize>);
+        impl Serialize for Counted<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.set(self.0.get() + 1);
+                maplit::btreemap! { "id" => self.0.get() }.serialize(serializer)
+            }
+        }
+        let count = std::cell::Cell::new(0);
+        let params = maplit::btreemap! { "items" => vec![Counted(&count), Counted(&count)] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?items[0][id]=1&items[1][id]=2");
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn test_inline_pairs() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Order {
+            Asc,
+            Desc,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Params {
+            attributes_to_crop: std::collections::BTreeMap<String, u32>,
+            sort: Vec<(String, Order)>,
+            labels: std::collections::BTreeMap<String, String>,
+        }
+
+        let params = Params {
+            attributes_to_crop: maplit::btreemap! { String::from("overview") => 5, String::from("title") => 10 },
+            sort: vec![
+                (String::from("price"), Order::Asc),
+                (String::from("date"), Order::Desc),
+            ],
+            labels: maplit::btreemap! { String::from("a:b") => String::from("c,d e") },
+        };
+
+        let config = Config::new().inline_pairs(":");
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?attributesToCrop=overview:5,title:10&sort=price:asc,date:desc&labels=a%3Ab:c%2Cd+e");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .inline_pairs("~")
+            .sequence_style(SequenceStyle::SpaceDelimited);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?attributesToCrop=overview~5%20title~10&sort=price~asc%20date~desc&labels=a%3Ab~c%2Cd+e");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let config = Config::new()
+            .inline_pairs(":")
+            .sequence_style(SequenceStyle::Repeated);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?attributesToCrop=overview:5&attributesToCrop=title:10&sort=price:asc&sort=date:desc&labels=a%3Ab:c%2Cd+e");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        let params = Params {
+            attributes_to_crop: std::collections::BTreeMap::new(),
+            sort: Vec::new(),
+            labels: std::collections::BTreeMap::new(),
+        };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        // a pair without its separator
+        insta::assert_snapshot!(from_str_with::<Params>("?sort=price", &config).unwrap_err(), @"invalid value: string \"price\", expected a key-value pair");
+        // a separator sharing a character with the delimiter of the sequence
+        let config = Config::new().inline_pairs(",");
+        let params = maplit::btreemap! { "sort" => vec![("price", "asc")] };
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `,`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        // a separator ending the parameter or its key
+        let config = Config::new().inline_pairs("=");
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `=`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+        let config = Config::new().inline_pairs("&");
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap_err(), @"Tried to join the elements of a sequence with `&`. Only delimiters made of ASCII punctuation other than `%`, `&`, `=`, `#` or the separators of the parameters, and sharing no character with the delimiters of the sequences holding them or the separator of the inline pairs, are supported.");
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json() {
+        use super::Json;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Range {
+            gte: u32,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            name: String,
+            range: Range,
+            matrix: Vec<Vec<u32>>,
+            tags: Vec<String>,
+            labels: std::collections::BTreeMap<String, u32>,
+            filter: Json<Vec<Range>>,
+        }
+
+        let params = Params {
+            name: String::from("[kefir]"),
+            range: Range { gte: 3 },
+            matrix: vec![vec![1, 2], vec![3]],
+            tags: vec![String::from("a"), String::from("b")],
+            labels: maplit::btreemap! { String::from("a") => 1 },
+            filter: Json(vec![Range { gte: 1 }]),
+        };
+
+        // only the wrapped value is sent as json by default
+        let url_params = to_string(&params);
+        insta::assert_snapshot!(url_params.unwrap_err(), @"Tried to serialize a struct in place of a value. Only simple values are supported on the right-hand side of a parameter.");
+
+        let config = Config::new().json_fallback(true);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=%5Bkefir%5D&range=%7B%22gte%22%3A3%7D&matrix=%5B%5B1%2C2%5D%2C%5B3%5D%5D&tags=a,b&labels=%7B%22a%22%3A1%7D&filter=%5B%7B%22gte%22%3A1%7D%5D");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+
+        // the supported values are still written as usual
+        let config = config.nesting_style(NestingStyle::Brackets);
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=%5Bkefir%5D&range[gte]=3&matrix=%5B%5B1%2C2%5D%2C%5B3%5D%5D&tags=a,b&labels[a]=1&filter=%5B%7B%22gte%22%3A1%7D%5D");
+
+        // the values that would be mistaken for JSON are sent as JSON
+        let config = Config::new().json_fallback(true).nested_delimiters(&["|"]);
+        let params = Params {
+            tags: vec![String::from("[1]")],
+            matrix: vec![vec![1], vec![]],
+            ..params
+        };
+        let url_params = to_string_with(&params, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?name=%5Bkefir%5D&range=%7B%22gte%22%3A3%7D&matrix=1,&tags=%5B%22%5B1%5D%22%5D&labels=%7B%22a%22%3A1%7D&filter=%5B%7B%22gte%22%3A1%7D%5D");
+        assert_eq!(
+            from_str_with::<Params>(&url_params, &config).unwrap(),
+            params
+        );
+        let lists = maplit::btreemap! { "lists" => vec![vec!["a"], vec!["{}"]] };
+        let url_params = to_string_with(&lists, &config).unwrap();
+        insta::assert_snapshot!(url_params, @"?lists=a,%7B%7D");
+        assert_eq!(
+            from_str_with::<std::collections::BTreeMap<String, Vec<Vec<String>>>>(
+                &url_params,
+                &config
+            )
+            .unwrap()["lists"],
+            lists["lists"]
+        );
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Wrapped {
+            filter: Json<Range>,
+        }
+        insta::assert_snapshot!(from_str::<Wrapped>("?filter=%7B%22gte%22%3A").unwrap_err(), @"EOF while parsing a value at line 1 column 7");
+    }
+
+    #[test]
+    fn test_toplevel_pairs() {
+        #[derive(Serialize)]
+        struct Param {
+            name: &'static str,
+            value: Option<u32>,
+        }
+
+        let params = [("a", "1"), ("a", "2"), ("b", "c d")];
+        insta::assert_snapshot!(to_string(&params[..]).unwrap(), @"?a=1&a=2&b=c+d");
+        let params = vec![(String::from("tags"), vec!["x", "y"])];
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?tags=x,y");
+
+        let params = vec![
+            Param {
+                name: "limit",
+                value: Some(10),
+            },
+            Param {
+                name: "offset",
+                value: None,
+            },
+        ];
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?limit=10");
+        let config = Config::new().none_policy(OmitPolicy::Null);
+        insta::assert_snapshot!(to_string_with(&params, &config).unwrap(), @"?limit=10&offset=null");
+
+        // other sequences are still rejected
+        insta::assert_snapshot!(to_string(&vec![1, 2]).unwrap_err(), @"Tried to serialize a sequence at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(to_string(&vec![("a", 1, 2)]).unwrap_err(), @"Tried to serialize a sequence at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        #[derive(Serialize)]
+        struct Range {
+            gte: u32,
+            lt: u32,
+        }
+        let params = vec![Range { gte: 1, lt: 2 }];
+        insta::assert_snapshot!(to_string(&params).unwrap_err(), @"Tried to serialize a sequence at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+
+        // nothing is written before the first pair
+        let mut writer = Vec::new();
+        assert!(to_writer(&mut writer, &vec![1, 2]).is_err());
+        assert!(writer.is_empty());
+        let params: Vec<(&str, &str)> = Vec::new();
+        insta::assert_snapshot!(to_string(&params).unwrap(), @"?");
+    }
+
+    #[test]
+    fn test_merge() {
+        #[derive(Serialize)]
+        struct Pagination {
+            offset: usize,
+            limit: Option<usize>,
+        }
+
+        #[derive(Serialize)]
+        struct Sort {
+            sort: Vec<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        enum Search {
+            ByName { name: &'static str },
+        }
+
+        let pagination = Pagination {
+            offset: 20,
+            limit: None,
+        };
+        let sort = Sort {
+            sort: vec!["price:asc"],
+        };
+        let search = Search::ByName { name: "kefir" };
+
+        insta::assert_snapshot!(to_string(&(&pagination, &sort)).unwrap(), @"?offset=20&sort=price%3Aasc");
+        insta::assert_snapshot!(to_string(&[("a", "1"), ("a", "2")]).unwrap(), @"?a=1&a=2");
+        insta::assert_snapshot!(to_string(&(&pagination, ("q", "doggo"))).unwrap(), @"?offset=20&q=doggo");
+        insta::assert_snapshot!(to_string(&(&sort, None::<Pagination>, &[("q", "doggo")][..])).unwrap(), @"?sort=price%3Aasc&q=doggo");
+        insta::assert_snapshot!(to_string_many(&[&sort, &pagination, &search]).unwrap(), @"?sort=price%3Aasc&offset=20&name=kefir");
+        insta::assert_snapshot!(to_string_many(&[]).unwrap(), @"?");
+
+        let config = Config::new()
+            .prefix("")
+            .pair_separator(';')
+            .variant_tag("type");
+        insta::assert_snapshot!(to_string_many_with(&[&pagination, &search, &sort], &config).unwrap(), @"offset=20;type=ByName;name=kefir;sort=price%3Aasc");
+
+        // the values of the tuple still need keys
+        insta::assert_snapshot!(to_string(&(&pagination, 1)).unwrap_err(), @"Tried to serialize a i32 at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(to_string(&(&pagination, (&sort,))).unwrap_err(), @"Tried to serialize a tuple at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(to_string(&("a", "1")).unwrap_err(), @"Tried to serialize a str at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+
+        // a pair must be written whole
+        struct Pair(&'static [&'static str]);
+        impl Serialize for Pair {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeTuple;
+
+                let mut tuple = serializer.serialize_tuple(2)?;
+                for part in self.0 {
+                    tuple.serialize_element(part)?;
+                }
+                tuple.end()
+            }
+        }
+        insta::assert_snapshot!(to_string(&(&sort, Pair(&["q"]))).unwrap_err(), @"Tried to serialize a tuple at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(to_string(&(&sort, Pair(&["q", "a", "b"]))).unwrap_err(), @"Tried to serialize a tuple at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+        insta::assert_snapshot!(to_string(&vec![Pair(&["q"])]).unwrap_err(), @"Tried to serialize a sequence at the top level. Only key-value shapes are supported at the top level of a query parameter.");
+    }
 }
@@ -0,0 +1,86 @@
+//! A value that can be left out, sent as null or sent.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A field telling apart a missing parameter from an explicit null, like an
+/// `Option<Option<T>>`.
+///
+/// ```
+/// use nyaup::Nullable;
+///
+/// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct Patch {
+///     name: Nullable<String>,
+///     age: Nullable<u8>,
+///     breed: Nullable<String>,
+/// }
+///
+/// let patch = Patch {
+///     name: Nullable::Value(String::from("kefir")),
+///     age: Nullable::Null,
+///     breed: Nullable::Absent,
+/// };
+/// let query = nyaup::to_string(&patch).unwrap();
+/// assert_eq!(query, "?name=kefir&age=null");
+/// assert_eq!(nyaup::from_str::<Patch>(&query).unwrap(), patch);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Nullable<T> {
+    /// The parameter is left out.
+    #[default]
+    Absent,
+    /// The parameter is sent with the null token of the
+    /// [`Config`](crate::Config).
+    Null,
+    /// The parameter is sent with the value, an empty string is sent as
+    /// `key=` whatever the [`OmitPolicy`](crate::OmitPolicy).
+    Value(T),
+}
+
+impl<T> From<Option<Option<T>>> for Nullable<T> {
+    fn from(value: Option<Option<T>>) -> Self {
+        match value {
+            None => Nullable::Absent,
+            Some(None) => Nullable::Null,
+            Some(Some(value)) => Nullable::Value(value),
+        }
+    }
+}
+
+impl<T> From<Nullable<T>> for Option<Option<T>> {
+    fn from(value: Nullable<T>) -> Self {
+        match value {
+            Nullable::Absent => None,
+            Nullable::Null => Some(None),
+            Nullable::Value(value) => Some(Some(value)),
+        }
+    }
+}
+
+impl<T> Serialize for Nullable<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Nullable::Absent => serializer.serialize_none(),
+            Nullable::Null => serializer.serialize_some(&None::<T>),
+            Nullable::Value(value) => serializer.serialize_some(&Some(value)),
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Nullable<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Option<T>>::deserialize(deserializer).map(Nullable::from)
+    }
+}
@@ -0,0 +1,52 @@
+//! A value written without being percent-encoded.
+
+use serde::{Serialize, Serializer};
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+/// The name of the newtype struct the serializers recognize.
+pub(crate) const NAME: &str = "$nyaup::private::Raw";
+
+/// A value that is already percent-encoded, or must be sent verbatim, and is
+/// written as-is.
+///
+/// The value can't contain a `#` or the separators of the
+/// [`Config`](crate::Config), `&` and `=` by default.
+///
+/// ```
+/// use nyaup::Raw;
+///
+/// #[derive(serde::Serialize)]
+/// struct Params {
+///     cursor: Raw<&'static str>,
+///     q: &'static str,
+/// }
+///
+/// let params = Params { cursor: Raw("b2Zmc2V0OjQy%3D%3D"), q: "kefir le chien" };
+/// let query = nyaup::to_string(&params).unwrap();
+/// assert_eq!(query, "?cursor=b2Zmc2V0OjQy%3D%3D&q=kefir+le+chien");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Raw<T>(pub T);
+
+impl<T> Serialize for Raw<T>
+where
+    T: AsRef<str>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(NAME, self.0.as_ref())
+    }
+}
+
+/// Make sure the raw value can't be mistaken for the end of the parameter.
+pub(crate) fn check(value: &str, config: &Config) -> Result<()> {
+    let reserved = config.reserved_chars();
+    match value.chars().find(|c| reserved.contains(c)) {
+        Some(c) => Err(Error::UnsafeRawValue(c)),
+        None => Ok(()),
+    }
+}
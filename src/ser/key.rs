@@ -0,0 +1,295 @@
+//! Internal serializer for the keys of a map
+
+use serde::ser::{Impossible, SerializeSeq};
+
+use crate::config::{Config, EnumStyle, UnitStructStyle};
+use crate::error::Error;
+
+/// Render a map key, percent-encoded, into a string.
+pub struct Serializer<'a> {
+    config: &'a Config,
+    sequence_allowed: bool,
+    /// Whether the key is wrapped in a [`Raw`](crate::Raw).
+    raw: bool,
+}
+
+impl<'a> Serializer<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Serializer {
+            config,
+            sequence_allowed: true,
+            raw: false,
+        }
+    }
+}
+
+impl<'a> ::serde::ser::Serializer for Serializer<'a> {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        match self.config.bool_style.token(v) {
+            Some(token) => Ok(token.to_string()),
+            None => Err(Error::UnsupportedKey("omitted bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.config.format_float(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.config.format_float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(self.config.encoding.encode(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        if self.raw {
+            crate::raw::check(v, self.config)?;
+            return Ok(v.to_string());
+        }
+        Ok(self.config.encoding.encode(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if let Some(encoded) = self.config.bytes_encoding.encode(v, self.config.encoding) {
+            return Ok(encoded);
+        }
+        if !self.sequence_allowed {
+            return Err(Error::UnsupportedKey("bytes"));
+        }
+        let mut serializer = self.serialize_seq(Some(v.len()))?;
+        for v in v {
+            serializer.serialize_element(v)?;
+        }
+        serializer.end()
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(Error::UnsupportedKey("none"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(Error::UnsupportedKey("unit"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        match self.config.unit_struct_style {
+            UnitStructStyle::Unit => Err(Error::UnsupportedKey("unit struct")),
+            UnitStructStyle::Name => Ok(self.config.encoding.encode(name)),
+        }
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        if self.config.enum_style == EnumStyle::Index {
+            return Ok(variant_index.to_string());
+        }
+        let variant = self.config.variant_case.apply(variant);
+        Ok(self.config.encoding.encode(&variant))
+    }
+
+    fn serialize_newtype_struct<T>(
+        mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        self.raw = name == crate::raw::NAME;
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        if !self.sequence_allowed {
+            return Err(Error::UnsupportedKey("sequence"));
+        }
+        Ok(SeqSerializer {
+            config: self.config,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(Error::UnsupportedKey("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(Error::UnsupportedKey("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(Error::UnsupportedKey("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(Error::UnsupportedKey("struct variant"))
+    }
+}
+
+/// A sequence used as a key, its elements are joined with the delimiter of
+/// the sequence style, or a comma when every element gets its own parameter.
+pub struct SeqSerializer<'a> {
+    config: &'a Config,
+    elements: Vec<String>,
+}
+
+impl ::serde::ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let element = value.serialize(Serializer {
+            config: self.config,
+            sequence_allowed: false,
+            raw: false,
+        })?;
+        self.elements
+            .push(self.config.escape_element(&element, 1)?.into_owned());
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        let delimiter = self.config.sequence_style.delimiter().unwrap_or(",");
+        Ok(self.elements.join(delimiter))
+    }
+}
+
+impl ::serde::ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ::serde::ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
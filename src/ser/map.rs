@@ -1,25 +1,173 @@
+use crate::config::{Compat, Config, NestingStyle};
 use crate::error::Result;
+use std::borrow::Cow;
 use std::io;
 
 pub struct Serializer<'a, W> {
-    seq: bool,
-    key: &'a str,
+    /// The key of the value this map is nested in.
+    parent: Option<&'a str>,
+    /// How many maps this one is nested in.
+    depth: usize,
+    /// How the keys are nested in `parent`.
+    nesting: NestingStyle,
+    /// The rendered key waiting for its value.
+    key: String,
     writer: &'a mut W,
-    first_param: bool,
+    config: &'a Config,
+    first_param: &'a mut bool,
+    /// The variant tag written along with the keys, which can't be one of
+    /// them.
+    tag: Option<&'static str>,
 }
 
 impl<'a, W> Serializer<'a, W>
 where
     W: io::Write,
 {
-    pub fn new(writer: &'a mut W, seq: bool) -> Self {
+    pub fn new(first_param: &'a mut bool, writer: &'a mut W, config: &'a Config) -> Self {
         Serializer {
-            seq,
-            key: "",
+            parent: None,
+            depth: 0,
+            nesting: config.nesting_style,
+            key: String::new(),
             writer,
-            first_param: true,
+            config,
+            first_param,
+            tag: None,
         }
     }
+
+    /// A struct or a map written in place of the value of `parent`.
+    pub fn new_nested(
+        parent: &'a str,
+        depth: usize,
+        first_param: &'a mut bool,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
+        Serializer {
+            parent: Some(parent),
+            depth,
+            nesting: config.nesting_style,
+            key: String::new(),
+            writer,
+            config,
+            first_param,
+            tag: None,
+        }
+    }
+
+    /// An element of a sequence of structs, its fields written in brackets
+    /// after `parent`, the key of the sequence and the index of the element.
+    pub fn new_element(
+        parent: &'a str,
+        first_param: &'a mut bool,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
+        Serializer {
+            parent: Some(parent),
+            depth: 1,
+            nesting: NestingStyle::Brackets,
+            key: String::new(),
+            writer,
+            config,
+            first_param,
+            tag: None,
+        }
+    }
+
+    pub fn with_tag(mut self, tag: Option<&'static str>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    pub fn serialize_pair<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        if let Some(tag) = self.tag {
+            // the pair would be read as the tag, and dropped
+            if self.config.encoding.decode(key).is_ok_and(|key| key == tag) {
+                return Err(crate::Error::ConflictingVariantTag(tag));
+            }
+        }
+        let key = match self.parent {
+            Some(parent) => Cow::Owned(self.nesting.nest(parent, key)),
+            None => self.config.nesting_style.escape(key),
+        };
+        #[cfg(feature = "json")]
+        if self.config.json_fallback {
+            return self.serialize_pair_or_json(&key, value);
+        }
+        let simple = super::simple::Serializer::new_from_map(
+            self.depth,
+            self.first_param,
+            &key,
+            &mut *self.writer,
+            self.config,
+        );
+        value.serialize(simple)
+    }
+
+    /// Write the pair, sending its value as JSON if the format can't express
+    /// it or if it would be read back as JSON.
+    #[cfg(feature = "json")]
+    fn serialize_pair_or_json<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        // nothing is written until the value is known to be supported
+        let mut buffer = Vec::new();
+        let mut first_param = *self.first_param;
+        let simple = super::simple::Serializer::new_from_map(
+            self.depth,
+            &mut first_param,
+            key,
+            &mut buffer,
+            self.config,
+        );
+        let json = match value.serialize(simple) {
+            // a value read back as JSON is sent as JSON
+            Ok(()) => match self.json_lookalike(&buffer) {
+                Some(start) => {
+                    Some(serde_json::to_string(value)?).filter(|json| json.starts_with(start))
+                }
+                None => None,
+            },
+            Err(crate::Error::UnsupportedNestedStruct(_)) => Some(serde_json::to_string(value)?),
+            Err(error) => return Err(error),
+        };
+        match json {
+            Some(json) => super::simple::write_param(
+                self.writer,
+                self.first_param,
+                (key, &self.config.encoding.encode(&json)),
+                self.config,
+            ),
+            None => {
+                self.writer.write_all(&buffer)?;
+                *self.first_param = first_param;
+                Ok(())
+            }
+        }
+    }
+
+    /// The start of the first value of the parameters if it's the start of a
+    /// JSON array or object.
+    #[cfg(feature = "json")]
+    fn json_lookalike(&self, params: &[u8]) -> Option<char> {
+        let params = std::str::from_utf8(params).ok()?;
+        let separator = self.config.pair_separator;
+        let (_, value) = params
+            .strip_prefix(separator)
+            .unwrap_or(params)
+            .split(separator)
+            .next()?
+            .split_once(self.config.key_value_separator)?;
+        let value = self.config.encoding.decode(value).ok()?;
+        value.chars().next().filter(|c| ['[', '{'].contains(c))
+    }
 }
 
 impl<'a, W> ::serde::ser::SerializeMap for Serializer<'a, W>
@@ -33,15 +181,8 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        if !self.first_param {
-            write!(self.writer, "&")?;
-        }
-        let simple = super::simple::Serializer::new_from_toplevel(false, &mut self.first_param, self.key, &mut *self.writer);
-        key.serialize(simple)?;
-        // write!(self.writer, "=")?;
-        if self.first_param {
-            self.first_param = false;
-        }
+        // the key is only written with its value, which may be omitted
+        self.key = key.serialize(super::key::Serializer::new(self.config))?;
         Ok(())
     }
 
@@ -49,9 +190,8 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        let simple = super::simple::Serializer::new_from_toplevel(false, &mut self.first_param, &self.key, &mut *self.writer);
-        value.serialize(simple)?;
-        Ok(())
+        let key = std::mem::take(&mut self.key);
+        self.serialize_pair(&key, value)
     }
 
     fn end(self) -> Result<()> {
@@ -70,9 +210,12 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        let simple = super::simple::Serializer::new_from_toplevel(true, &mut self.first_param, key, &mut *self.writer);
-        value.serialize(simple)?;
-        Ok(())
+        // yaup writes the field names as-is
+        if self.config.compat == Compat::Yaup {
+            return self.serialize_pair(key, value);
+        }
+        let key = self.config.encoding.encode(key);
+        self.serialize_pair(&key, value)
     }
 
     fn end(self) -> Result<()> {
@@ -102,3 +245,47 @@ where
         Ok(())
     }
 }
+
+/// A map written in place of a value.
+pub enum ValueSerializer<'a, W> {
+    /// The entries are nested parameters, following the
+    /// [`NestingStyle`].
+    Nested(Serializer<'a, W>),
+    /// The entries are inline pairs, elements of a sequence.
+    Inline(super::seq::Serializer<'a, W>),
+}
+
+impl<'a, W> ::serde::ser::SerializeMap for ValueSerializer<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = crate::error::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        match self {
+            ValueSerializer::Nested(map) => map.serialize_key(key),
+            ValueSerializer::Inline(seq) => seq.serialize_key(key),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        match self {
+            ValueSerializer::Nested(map) => map.serialize_value(value),
+            ValueSerializer::Inline(seq) => seq.serialize_value(value),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        match self {
+            ValueSerializer::Nested(map) => ::serde::ser::SerializeMap::end(map),
+            ValueSerializer::Inline(seq) => ::serde::ser::SerializeMap::end(seq),
+        }
+    }
+}
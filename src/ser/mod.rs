@@ -1,23 +1,117 @@
 //! Serialize a Rust data structure into URL parameters string.
 
+mod key;
 mod map;
+mod pairs;
+mod row;
 mod seq;
 mod simple;
 
+use crate::config::{Config, EnumStyle};
 use crate::error::Result;
 use std::io;
 
 /// A structure for serializing Rust values into URL parameters string.
 pub struct Serializer<W> {
     writer: W,
+    config: Config,
+    first_param: bool,
+    /// The variant waiting to be written in the tag parameter.
+    variant: Option<String>,
+    /// Whether the values of a top-level tuple are being written, sharing a
+    /// single prefix.
+    merging: bool,
+    /// Whether the tag parameter was written, its name can't be used by the
+    /// other parameters.
+    tagged: bool,
 }
 
 impl<W> Serializer<W>
 where
     W: io::Write,
 {
-    fn new(writer: W) -> Self {
-        Serializer { writer }
+    /// Create a serializer writing into the given IO stream with the default
+    /// [`Config`].
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::default())
+    }
+
+    /// Create a serializer writing into the given IO stream with the given
+    /// [`Config`].
+    ///
+    /// ```
+    /// use nyaup::{Config, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     doggo: &'static str,
+    ///     catto: Option<&'static str>,
+    /// }
+    ///
+    /// let config = Config::new().prefix("").pair_separator(';');
+    /// let mut serializer = Serializer::with_config(Vec::new(), config);
+    /// Params { doggo: "kefir", catto: Some("echo") }.serialize(&mut serializer).unwrap();
+    /// assert_eq!(serializer.into_inner(), b"doggo=kefir;catto=echo");
+    /// ```
+    pub fn with_config(writer: W, config: Config) -> Self {
+        Serializer {
+            writer,
+            config,
+            first_param: true,
+            variant: None,
+            merging: false,
+            tagged: false,
+        }
+    }
+
+    /// Unwrap the underlying IO stream.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> Serializer<W>
+where
+    W: io::Write,
+{
+    fn start_params(&mut self) -> Result<map::Serializer<'_, W>> {
+        if !self.merging {
+            self.config.check_separators()?;
+            write!(self.writer, "{}", self.config.prefix)?;
+            self.first_param = true;
+            self.tagged = false;
+        }
+        if let (Some(tag), Some(variant)) = (self.config.variant_tag, self.variant.take()) {
+            let tag = self.config.encoding.encode(tag);
+            simple::write_param(
+                &mut self.writer,
+                &mut self.first_param,
+                (&tag, &variant),
+                &self.config,
+            )?;
+            self.tagged = true;
+        }
+        let tag = self.config.variant_tag.filter(|_| self.tagged);
+        Ok(
+            map::Serializer::new(&mut self.first_param, &mut self.writer, &self.config)
+                .with_tag(tag),
+        )
+    }
+
+    /// Keep the variant for the tag parameter, if the configuration has one.
+    fn tag_variant(&mut self, variant_index: u32, variant: &str) -> bool {
+        if self.config.variant_tag.is_none() {
+            return false;
+        }
+        self.variant = Some(match self.config.enum_style {
+            EnumStyle::Index => variant_index.to_string(),
+            _ => {
+                let variant = self.config.variant_case.apply(variant);
+                self.config.encoding.encode(&variant)
+            }
+        });
+        true
     }
 }
 
@@ -28,9 +122,9 @@ where
     type Ok = ();
     type Error = crate::error::Error;
 
-    type SerializeSeq = serde::ser::Impossible<Self::Ok, Self::Error>;
-    type SerializeTuple = serde::ser::Impossible<Self::Ok, Self::Error>;
-    type SerializeTupleStruct = serde::ser::Impossible<Self::Ok, Self::Error>;
+    type SerializeSeq = pairs::Serializer<'a, W>;
+    type SerializeTuple = Merge<'a, W>;
+    type SerializeTupleStruct = Merge<'a, W>;
     type SerializeTupleVariant = serde::ser::Impossible<Self::Ok, Self::Error>;
     type SerializeMap = map::Serializer<'a, W>;
     type SerializeStruct = map::Serializer<'a, W>;
@@ -61,6 +155,11 @@ where
         Err(Self::Error::UnsupportedAtTopLevel("i64"))
     }
 
+    #[inline]
+    fn serialize_i128(self, _v: i128) -> Result<()> {
+        Err(Self::Error::UnsupportedAtTopLevel("i128"))
+    }
+
     #[inline]
     fn serialize_u8(self, _v: u8) -> Result<()> {
         Err(Self::Error::UnsupportedAtTopLevel("u8"))
@@ -81,6 +180,11 @@ where
         Err(Self::Error::UnsupportedAtTopLevel("u64"))
     }
 
+    #[inline]
+    fn serialize_u128(self, _v: u128) -> Result<()> {
+        Err(Self::Error::UnsupportedAtTopLevel("u128"))
+    }
+
     #[inline]
     fn serialize_f32(self, _v: f32) -> Result<()> {
         Err(Self::Error::UnsupportedAtTopLevel("f32"))
@@ -108,7 +212,7 @@ where
 
     #[inline]
     fn serialize_none(self) -> Result<()> {
-        Ok(())
+        self.serialize_unit()
     }
 
     #[inline]
@@ -121,6 +225,10 @@ where
 
     #[inline]
     fn serialize_unit(self) -> Result<()> {
+        // a newtype variant holding nothing still writes its tag
+        if self.variant.is_some() {
+            self.start_params()?;
+        }
         Ok(())
     }
 
@@ -133,10 +241,14 @@ where
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
     ) -> Result<()> {
-        Err(Self::Error::UnsupportedAtTopLevel("unit variant"))
+        if !self.tag_variant(variant_index, variant) {
+            return Err(Self::Error::UnsupportedAtTopLevel("unit variant"));
+        }
+        self.start_params()?;
+        Ok(())
     }
 
     #[inline]
@@ -151,33 +263,49 @@ where
     fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         value: &T,
     ) -> Result<()>
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
+        self.tag_variant(variant_index, variant);
         value.serialize(self)
     }
 
     #[inline]
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(Self::Error::UnsupportedAtTopLevel("sequence"))
+        Ok(pairs::Serializer::new(self))
     }
 
     #[inline]
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Err(Self::Error::UnsupportedAtTopLevel("tuple"))
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        if self.merging {
+            // a pair among the values, like the elements of a top-level array of pairs
+            if len == 2 {
+                return Ok(Merge {
+                    serializer: self,
+                    pair: Some(Pair::Key),
+                });
+            }
+            return Err(Self::Error::UnsupportedAtTopLevel("tuple"));
+        }
+        self.start_params()?;
+        self.merging = true;
+        Ok(Merge {
+            serializer: self,
+            pair: None,
+        })
     }
 
     #[inline]
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Err(Self::Error::UnsupportedAtTopLevel("tuple struct"))
+        self.serialize_tuple(len)
     }
 
     #[inline]
@@ -193,26 +321,100 @@ where
 
     #[inline]
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        write!(self.writer, "?")?;
-        Ok(map::Serializer::new(&mut self.writer, true))
+        self.start_params()
     }
 
     #[inline]
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        write!(self.writer, "?")?;
-        Ok(map::Serializer::new(&mut self.writer, false))
+        self.start_params()
     }
 
     #[inline]
     fn serialize_struct_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        write!(self.writer, "?")?;
-        Ok(map::Serializer::new(&mut self.writer, false))
+        self.tag_variant(variant_index, variant);
+        self.start_params()
+    }
+}
+
+/// The values of a top-level tuple, written one after the other under a
+/// single prefix.
+pub struct Merge<'a, W> {
+    serializer: &'a mut Serializer<W>,
+    /// Set when writing a pair instead.
+    pair: Option<Pair>,
+}
+
+/// How far a pair among the values of a tuple was written.
+enum Pair {
+    /// Waiting for its key.
+    Key,
+    /// Waiting for its value, along with the rendered key.
+    Value(String),
+    /// Both were written.
+    Done,
+}
+
+impl<'a, W> ::serde::ser::SerializeTuple for Merge<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = crate::error::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let serializer = &mut *self.serializer;
+        let Some(pair) = &mut self.pair else {
+            return value.serialize(serializer);
+        };
+        *pair = match std::mem::replace(pair, Pair::Done) {
+            Pair::Key => Pair::Value(value.serialize(key::Serializer::new(&serializer.config))?),
+            Pair::Value(key) => {
+                serializer.start_params()?.serialize_pair(&key, value)?;
+                Pair::Done
+            }
+            Pair::Done => return Err(Self::Error::UnsupportedAtTopLevel("tuple")),
+        };
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        match self.pair {
+            None => {
+                self.serializer.merging = false;
+                Ok(())
+            }
+            Some(Pair::Done) => Ok(()),
+            // a pair missing its key or its value
+            Some(_) => Err(Self::Error::UnsupportedAtTopLevel("tuple")),
+        }
+    }
+}
+
+impl<'a, W> ::serde::ser::SerializeTupleStruct for Merge<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = crate::error::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        ::serde::ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ::serde::ser::SerializeTuple::end(self)
     }
 }
 
@@ -223,7 +425,8 @@ where
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. not a struct, a sequence of pairs nor a
+///   tuple of those.
 /// * `T` contains a nested struct,
 /// * `T` contains a map.
 #[inline]
@@ -232,7 +435,22 @@ where
     W: io::Write,
     T: ::serde::ser::Serialize + ?Sized,
 {
-    let mut ser = Serializer::new(writer);
+    to_writer_with(writer, value, &Config::default())
+}
+
+/// Serialize the given data structure as URL parameters into the IO stream,
+/// following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`to_writer`].
+#[inline]
+pub fn to_writer_with<W, T>(writer: W, value: &T, config: &Config) -> Result<()>
+where
+    W: io::Write,
+    T: ::serde::ser::Serialize + ?Sized,
+{
+    let mut ser = Serializer::with_config(writer, config.clone());
     value.serialize(&mut ser)?;
     Ok(())
 }
@@ -245,16 +463,31 @@ where
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. not a struct, a sequence of pairs nor a
+///   tuple of those.
 /// * `T` contains a nested struct,
 /// * `T` contains a map.
 #[inline]
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ::serde::ser::Serialize + ?Sized,
+{
+    to_vec_with(value, &Config::default())
+}
+
+/// Serialize the given data structure as a byte vector containing URL
+/// parameters, following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`to_vec`].
+#[inline]
+pub fn to_vec_with<T>(value: &T, config: &Config) -> Result<Vec<u8>>
 where
     T: ::serde::ser::Serialize + ?Sized,
 {
     let mut writer = Vec::with_capacity(128);
-    to_writer(&mut writer, value)?;
+    to_writer_with(&mut writer, value, config)?;
     Ok(writer)
 }
 
@@ -265,7 +498,8 @@ where
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. not a struct, a sequence of pairs nor a
+///   tuple of those.
 /// * `T` contains a nested struct,
 /// * `T` contains a map.
 #[inline]
@@ -273,7 +507,84 @@ pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: ::serde::ser::Serialize + ?Sized,
 {
-    let vec = to_vec(value)?;
+    to_string_with(value, &Config::default())
+}
+
+/// Serialize the given data structure as a String of URL parameters,
+/// following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`to_string`].
+#[inline]
+pub fn to_string_with<T>(value: &T, config: &Config) -> Result<String>
+where
+    T: ::serde::ser::Serialize + ?Sized,
+{
+    let vec = to_vec_with(value, config)?;
     let string = String::from_utf8(vec)?;
     Ok(string)
 }
+
+/// The values given to [`to_string_many`], written as a top-level tuple.
+struct Many<'a>(&'a [&'a dyn erased_serde::Serialize]);
+
+impl ::serde::ser::Serialize for Many<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::ser::Serializer,
+    {
+        use ::serde::ser::SerializeTuple;
+
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for value in self.0 {
+            tuple.serialize_element(value)?;
+        }
+        tuple.end()
+    }
+}
+
+/// Serialize the given data structures as a single String of URL parameters,
+/// one after the other.
+///
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Pagination {
+///     offset: usize,
+///     limit: usize,
+/// }
+///
+/// #[derive(serde::Serialize)]
+/// struct Filters {
+///     breed: &'static str,
+/// }
+///
+/// let pagination = Pagination { offset: 20, limit: 10 };
+/// let filters = Filters { breed: "shiba" };
+/// let query = nyaup::to_string_many(&[&pagination, &filters]).unwrap();
+/// assert_eq!(query, "?offset=20&limit=10&breed=shiba");
+/// // a tuple of data structures is written the same way
+/// assert_eq!(nyaup::to_string(&(&pagination, &filters)).unwrap(), query);
+/// ```
+///
+/// # Errors
+///
+/// See [`to_string`].
+#[inline]
+pub fn to_string_many(values: &[&dyn erased_serde::Serialize]) -> Result<String> {
+    to_string_many_with(values, &Config::default())
+}
+
+/// Serialize the given data structures as a single String of URL parameters,
+/// one after the other, following the given [`Config`].
+///
+/// # Errors
+///
+/// See [`to_string`].
+#[inline]
+pub fn to_string_many_with(
+    values: &[&dyn erased_serde::Serialize],
+    config: &Config,
+) -> Result<String> {
+    to_string_with(&Many(values), config)
+}
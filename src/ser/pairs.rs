@@ -0,0 +1,345 @@
+//! Internal serializer for the top-level sequences of key-value pairs
+
+use std::io;
+
+use serde::ser::{Impossible, Serialize};
+
+use crate::error::{Error, Result};
+
+/// A top-level sequence whose elements are written as parameters, in order.
+pub struct Serializer<'a, W> {
+    serializer: &'a mut super::Serializer<W>,
+    /// Whether the values were already merged with others, sharing their
+    /// prefix.
+    merging: bool,
+}
+
+impl<'a, W> Serializer<'a, W>
+where
+    W: io::Write,
+{
+    pub fn new(serializer: &'a mut super::Serializer<W>) -> Self {
+        let merging = serializer.merging;
+        Serializer {
+            serializer,
+            merging,
+        }
+    }
+}
+
+impl<'a, W> ::serde::ser::SerializeSeq for Serializer<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(Pair {
+            serializer: &mut *self.serializer,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        // the prefix is still written when no pair was
+        if !self.serializer.merging {
+            self.serializer.start_params()?;
+        }
+        self.serializer.merging = self.merging;
+        Ok(())
+    }
+}
+
+/// An element of the sequence, a 2-tuple, a tuple struct with two fields, or
+/// a struct whose fields are `key` or `name`, then `value`: the first is the
+/// key, the second the value.
+struct Pair<'b, W> {
+    serializer: &'b mut super::Serializer<W>,
+}
+
+impl<'b, W> Pair<'b, W> {
+    fn fields(self, len: usize) -> Result<Fields<'b, W>> {
+        match len {
+            2 => Ok(Fields {
+                serializer: self.serializer,
+                key: None,
+                len: 0,
+            }),
+            _ => Err(Error::UnsupportedAtTopLevel("sequence")),
+        }
+    }
+}
+
+impl<'b, W> ::serde::ser::Serializer for Pair<'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Fields<'b, W>;
+    type SerializeTupleStruct = Fields<'b, W>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Fields<'b, W>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.fields(len)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.fields(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.fields(len)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::UnsupportedAtTopLevel("sequence"))
+    }
+}
+
+/// The key then the value of a pair.
+struct Fields<'b, W> {
+    serializer: &'b mut super::Serializer<W>,
+    /// The rendered key waiting for its value.
+    key: Option<String>,
+    len: usize,
+}
+
+impl<'b, W> Fields<'b, W>
+where
+    W: io::Write,
+{
+    fn push<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.len += 1;
+        match (self.len, self.key.take()) {
+            (1, _) => {
+                let key = value.serialize(super::key::Serializer::new(&self.serializer.config))?;
+                self.key = Some(key);
+                Ok(())
+            }
+            // nothing is written before the first pair is known to be one
+            (2, Some(key)) => {
+                let pair = self.serializer.start_params()?.serialize_pair(&key, value);
+                self.serializer.merging = true;
+                pair
+            }
+            _ => Err(Error::UnsupportedAtTopLevel("sequence")),
+        }
+    }
+
+    /// Make sure the pair got both its key and its value.
+    fn finish(self) -> Result<()> {
+        match self.len {
+            2 => Ok(()),
+            _ => Err(Error::UnsupportedAtTopLevel("sequence")),
+        }
+    }
+}
+
+impl<'b, W> ::serde::ser::SerializeTuple for Fields<'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'b, W> ::serde::ser::SerializeTupleStruct for Fields<'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'b, W> ::serde::ser::SerializeStruct for Fields<'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        // the names of the other fields would be lost
+        let expected: &[&str] = match self.len {
+            0 => &["key", "name"],
+            _ => &["value"],
+        };
+        if !expected.contains(&key) {
+            return Err(Error::UnsupportedAtTopLevel("sequence"));
+        }
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
@@ -0,0 +1,404 @@
+//! Internal serializer for the elements of a sequence of structs
+
+use serde::ser::Serialize;
+
+use crate::config::{Compat, Config};
+use crate::error::Error;
+
+/// The fields of an element and their values, rendered as elements of a
+/// sequence.
+pub type Cells = Vec<(String, String)>;
+
+/// A struct or a map element, written following the
+/// [`StructSequenceStyle`](crate::StructSequenceStyle).
+pub enum Row {
+    /// The fields rendered as the cells of their columns.
+    Cells(Cells),
+    /// The parameters of the fields, along with whether the next parameter
+    /// is still the first one.
+    Params(Vec<u8>, bool),
+}
+
+/// Tell whether an element of a sequence is a struct or a map, writing its
+/// fields as the parameters of the element or as the cells of the columns.
+///
+/// Any other value gives `None`, to be written as a plain element.
+pub struct Serializer<'a> {
+    config: &'a Config,
+    /// The key of the element, `items[0]`, when its fields are written as
+    /// parameters.
+    parent: Option<String>,
+    first_param: bool,
+}
+
+impl<'a> Serializer<'a> {
+    pub fn new(parent: Option<String>, first_param: bool, config: &'a Config) -> Self {
+        Serializer {
+            config,
+            parent,
+            first_param,
+        }
+    }
+
+    fn fields(self, is_struct: bool) -> Fields<'a> {
+        Fields {
+            config: self.config,
+            parent: self.parent,
+            first_param: self.first_param,
+            is_struct,
+            key: String::new(),
+            cells: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+}
+
+impl<'a> ::serde::ser::Serializer for Serializer<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    type SerializeSeq = Fields<'a>;
+    type SerializeTuple = Fields<'a>;
+    type SerializeTupleStruct = Fields<'a>;
+    type SerializeTupleVariant = Fields<'a>;
+    type SerializeMap = Fields<'a>;
+    type SerializeStruct = Fields<'a>;
+    type SerializeStructVariant = Fields<'a>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_i128(self, _v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == crate::raw::NAME {
+            return Ok(None);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(None)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(self.fields(false))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(self.fields(false))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(self.fields(false))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(self.fields(false))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self.fields(true))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self.fields(true))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(self.fields(false))
+    }
+}
+
+/// Collect the fields of a struct or a map, skip the content of anything else.
+pub struct Fields<'a> {
+    config: &'a Config,
+    parent: Option<String>,
+    first_param: bool,
+    is_struct: bool,
+    /// The rendered key waiting for its value.
+    key: String,
+    cells: Cells,
+    params: Vec<u8>,
+}
+
+impl<'a> Fields<'a> {
+    fn push<T>(&mut self, key: String, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &self.parent {
+            Some(parent) => super::map::Serializer::new_element(
+                parent,
+                &mut self.first_param,
+                &mut self.params,
+                self.config,
+            )
+            .serialize_pair(&key, value),
+            None => {
+                let cell = super::simple::render(value, 1, self.config)?;
+                self.cells.push((key, cell));
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> Option<Row> {
+        if !self.is_struct {
+            return None;
+        }
+        Some(match self.parent {
+            Some(_) => Row::Params(self.params, self.first_param),
+            None => Row::Cells(self.cells),
+        })
+    }
+}
+
+impl<'a> ::serde::ser::SerializeSeq for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeTuple for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeTupleStruct for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeTupleVariant for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeMap for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = key.serialize(super::key::Serializer::new(self.config))?;
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = std::mem::take(&mut self.key);
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeStruct for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // yaup writes the field names as-is
+        let key = match self.config.compat {
+            Compat::Yaup => key.to_string(),
+            _ => self.config.encoding.encode(key),
+        };
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'a> ::serde::ser::SerializeStructVariant for Fields<'a> {
+    type Ok = Option<Row>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
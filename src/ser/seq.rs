@@ -1,21 +1,249 @@
 use std::io;
 
+use crate::config::{Config, NestingStyle, OmitPolicy, SequenceStyle, StructSequenceStyle};
+use crate::error::Error;
+
+use super::row::{Cells, Row};
+
 pub struct Serializer<'a, W> {
+    first_param: &'a mut bool,
+    is_key: bool,
+    /// The parameter is already opened, the elements are written right away.
+    in_param: bool,
+    /// How many sequences this one is an element of.
+    depth: usize,
     key: &'a str,
     writer: &'a mut W,
-    first_param: bool,
+    config: &'a Config,
+    len: usize,
+    /// The parameter holding the variant of a tuple variant, written last.
+    tag: Option<(String, String)>,
+    /// Whether the elements are structs, written with the
+    /// [`StructSequenceStyle`].
+    structs: bool,
+    /// The fields of the elements, waiting to be written as columns.
+    rows: Vec<Cells>,
+    /// The rendered parts of a 2-tuple written as an inline pair.
+    parts: Option<Vec<String>>,
+    /// The rendered key of a map entry waiting for its value.
+    pair_key: String,
+    /// Whether the first element was written empty.
+    first_empty: bool,
 }
 
 impl<'a, W> Serializer<'a, W>
 where
     W: io::Write,
 {
-    pub fn new(key: &'a str, writer: &'a mut W) -> Self {
+    pub fn new(
+        first_param: &'a mut bool,
+        is_key: bool,
+        in_param: bool,
+        depth: usize,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
         Serializer {
+            first_param,
+            is_key,
+            in_param,
+            depth,
             key,
             writer,
-            first_param: true,
+            config,
+            len: 0,
+            tag: None,
+            structs: false,
+            rows: Vec::new(),
+            parts: None,
+            pair_key: String::new(),
+            first_empty: false,
+        }
+    }
+
+    /// A 2-tuple written as an inline pair, an element of `depth` sequences.
+    pub fn new_pair(
+        first_param: &'a mut bool,
+        is_key: bool,
+        in_param: bool,
+        depth: usize,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
+        Serializer {
+            parts: Some(Vec::with_capacity(2)),
+            ..Self::new(first_param, is_key, in_param, depth, key, writer, config)
+        }
+    }
+
+    pub fn with_tag(mut self, tag: (String, String)) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Render the key or the value of an inline pair, an element of `depth`
+    /// sequences.
+    fn render_part<T>(&self, value: &T, depth: usize) -> Result<String, Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let part = super::simple::render(value, depth, self.config)?;
+        Ok(self.config.escape_pair_part(&part, depth)?.into_owned())
+    }
+
+    /// Join the rendered parts of an inline pair.
+    fn join_pair(&self, key: &str, value: &str) -> String {
+        let separator = self.config.inline_pairs.unwrap_or_default();
+        format!("{key}{separator}{value}")
+    }
+
+    /// Write an element that is already rendered and escaped.
+    fn write_element(&mut self, element: &str) -> Result<(), Error> {
+        match self.config.delimiter(self.depth) {
+            Some(delimiter) => {
+                if self.len != 0 {
+                    self.config.check_level(self.depth)?;
+                    write!(self.writer, "{delimiter}")?;
+                } else if !self.in_param {
+                    super::simple::start_param(
+                        self.writer,
+                        self.first_param,
+                        self.is_key,
+                        self.key,
+                        self.config,
+                    )?;
+                }
+                write!(self.writer, "{element}")?;
+            }
+            None => {
+                let key = self.element_key();
+                super::simple::write_param(
+                    self.writer,
+                    self.first_param,
+                    (&key, element),
+                    self.config,
+                )?;
+            }
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The key of the next element when every element gets its own
+    /// parameter.
+    fn element_key(&self) -> String {
+        match self.config.sequence_style {
+            SequenceStyle::Brackets => format!("{}[]", self.key),
+            SequenceStyle::Indexed => format!("{}[{}]", self.key, self.len),
+            _ => self.key.to_string(),
+        }
+    }
+
+    /// Whether an empty sequence leaves nothing in place of the value, a
+    /// single empty element then being read back as no element at all.
+    fn empty_is_empty(&self) -> bool {
+        self.depth != 0 || self.in_param || self.config.empty_seq_policy == OmitPolicy::Empty
+    }
+
+    /// Write the element if it's a struct and the sequences of structs are
+    /// supported, returning whether it was.
+    fn serialize_struct_element<T>(&mut self, value: &T) -> Result<bool, Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let style = self.config.struct_sequence_style;
+        // only the sequences written in their own parameters can hold structs
+        if style == StructSequenceStyle::Unsupported || self.depth != 0 || self.in_param {
+            return Ok(false);
+        }
+        let parent = match style {
+            StructSequenceStyle::Indexed => Some(format!("{}[{}]", self.key, self.len)),
+            _ => None,
+        };
+        let row = super::row::Serializer::new(parent, *self.first_param, self.config);
+        match (value.serialize(row)?, self.structs) {
+            (Some(row), _) if self.structs || self.len == 0 => {
+                self.structs = true;
+                match row {
+                    Row::Cells(cells) => self.rows.push(cells),
+                    // nothing would be left to tell the element apart
+                    Row::Params(params, _) if params.is_empty() => {
+                        return Err(Error::UnsupportedNestedStruct(
+                            "struct element with every field omitted",
+                        ));
+                    }
+                    Row::Params(params, first_param) => {
+                        self.writer.write_all(&params)?;
+                        *self.first_param = first_param;
+                    }
+                }
+                Ok(true)
+            }
+            (None, false) => Ok(false),
+            _ => Err(Error::UnsupportedNestedStruct(
+                "sequence mixing structs and values",
+            )),
+        }
+    }
+
+    /// Write a parameter per field of the elements, holding the sequence of
+    /// its values.
+    fn write_columns(&mut self) -> Result<(), Error> {
+        let fields: Vec<&str> = self.rows[0].iter().map(|(field, _)| &**field).collect();
+        // a field missing from an element couldn't be told apart from an empty one
+        let same_fields = |row: &Cells| {
+            row.len() == fields.len() && row.iter().all(|(field, _)| fields.contains(&&**field))
+        };
+        if !self.rows.iter().all(same_fields) {
+            return Err(Error::UnsupportedNestedStruct(
+                "sequence of structs with differing fields",
+            ));
+        }
+        for field in &fields {
+            let column: Vec<&str> = self
+                .rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .find(|(key, _)| key == field)
+                        .map_or("", |(_, cell)| cell.as_str())
+                })
+                .collect();
+            // a field with nothing to show in any element is left out
+            if column.iter().all(|cell| cell.is_empty()) {
+                continue;
+            }
+            let key = NestingStyle::Dotted.nest(self.key, field);
+            match self.config.delimiter(0) {
+                Some(delimiter) => {
+                    self.config.check_level(0)?;
+                    super::simple::write_param(
+                        self.writer,
+                        self.first_param,
+                        (&key, &column.join(delimiter)),
+                        self.config,
+                    )?;
+                }
+                None => {
+                    for (index, cell) in column.iter().enumerate() {
+                        let key = match self.config.sequence_style {
+                            SequenceStyle::Brackets => format!("{key}[]"),
+                            SequenceStyle::Indexed => format!("{key}[{index}]"),
+                            _ => key.clone(),
+                        };
+                        super::simple::write_param(
+                            self.writer,
+                            self.first_param,
+                            (&key, cell),
+                            self.config,
+                        )?;
+                    }
+                }
+            }
         }
+        Ok(())
     }
 }
 
@@ -30,19 +258,108 @@ where
     where
         T: ?Sized + serde::Serialize,
     {
-        if !self.first_param {
-            write!(self.writer, ",")?;
+        if self.parts.is_some() {
+            let part = self.render_part(value, self.depth)?;
+            self.parts.get_or_insert_default().push(part);
+            return Ok(());
         }
-        let simple = super::simple::Serializer::new_from_seq(&mut self.first_param, self.key, &mut *self.writer);
-        value.serialize(simple)?;
-        if self.first_param {
-            self.first_param = false;
+        if self.serialize_struct_element(value)? {
+            self.len += 1;
+            return Ok(());
         }
+        if let Some(delimiter) = self.config.delimiter(self.depth) {
+            // a single empty element leaves nothing else to check it
+            self.config.check_level(self.depth)?;
+            if self.len != 0 {
+                write!(self.writer, "{delimiter}")?;
+            } else if !self.in_param {
+                // the parameter is only opened once we know the sequence isn't empty
+                super::simple::start_param(
+                    self.writer,
+                    self.first_param,
+                    self.is_key,
+                    self.key,
+                    self.config,
+                )?;
+            }
+            if self.len == 0 {
+                let element = super::simple::render(value, self.depth + 1, self.config)?;
+                self.first_empty = element.is_empty();
+                write!(self.writer, "{element}")?;
+            } else {
+                let simple = super::simple::Serializer::new_from_seq(
+                    self.depth + 1,
+                    self.first_param,
+                    self.key,
+                    &mut *self.writer,
+                    self.config,
+                );
+                value.serialize(simple)?;
+            }
+        } else {
+            if self.len == 0 && self.empty_is_empty() {
+                self.first_empty =
+                    super::simple::render(value, self.depth + 1, self.config)?.is_empty();
+            }
+            let key = self.element_key();
+            let simple = super::simple::Serializer::new_from_seq_param(
+                self.depth + 1,
+                self.first_param,
+                &key,
+                &mut *self.writer,
+                self.config,
+            );
+            value.serialize(simple)?;
+        }
+        self.len += 1;
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if let Some(parts) = self.parts.take() {
+            let [key, value] = &parts[..] else {
+                return Err(Error::UnsupportedNestedStruct("tuple"));
+            };
+            let pair = self.join_pair(key, value);
+            if !self.in_param {
+                super::simple::start_param(
+                    self.writer,
+                    self.first_param,
+                    self.is_key,
+                    self.key,
+                    self.config,
+                )?;
+            }
+            write!(self.writer, "{pair}")?;
+            return Ok(());
+        }
+        if !self.rows.is_empty() {
+            self.write_columns()?;
+        }
+        if self.len == 1 && self.first_empty && self.empty_is_empty() {
+            return Err(Error::UnsupportedNestedStruct(
+                "sequence holding a single empty element",
+            ));
+        }
+        if self.len == 0 && !self.in_param {
+            let simple = super::simple::Serializer::new_from_toplevel(
+                self.is_key,
+                &mut *self.first_param,
+                self.key,
+                &mut *self.writer,
+                self.config,
+            );
+            simple.serialize_omitted(self.config.empty_seq_policy)?;
+        }
+        match self.tag {
+            Some((key, variant)) => super::simple::write_param(
+                self.writer,
+                self.first_param,
+                (&key, &variant),
+                self.config,
+            ),
+            None => Ok(()),
+        }
     }
 }
 
@@ -61,7 +378,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        ::serde::ser::SerializeSeq::end(self)
     }
 }
 
@@ -80,7 +397,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        ::serde::ser::SerializeSeq::end(self)
     }
 }
 
@@ -99,6 +416,37 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        ::serde::ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ::serde::ser::SerializeMap for Serializer<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = crate::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        // the entries are elements of the sequence
+        self.pair_key = self.render_part(key, self.depth + 1)?;
         Ok(())
     }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let key = std::mem::take(&mut self.pair_key);
+        let value = self.render_part(value, self.depth + 1)?;
+        let pair = self.join_pair(&key, &value);
+        self.write_element(&pair)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ::serde::ser::SerializeSeq::end(self)
+    }
 }
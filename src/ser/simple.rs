@@ -1,40 +1,293 @@
 //! Internal serializer for simple value
 
+use std::fmt::Display;
 use std::io;
 
 use serde::ser::SerializeSeq;
 
+use crate::config::{Compat, Config, EnumStyle, OmitPolicy, UnitStructStyle};
+
 pub struct Serializer<'a, W> {
     is_key: bool,
     first_param: &'a mut bool,
     key: &'a str,
     writer: &'a mut W,
-    sequence_allowed: bool,
+    config: &'a Config,
+    in_param: bool,
+    /// How many sequences the value is an element of.
+    seq_depth: usize,
+    /// How many maps the value is nested in.
+    depth: usize,
+    /// Whether the value is wrapped in a `Some`, a `None` inside it is an
+    /// explicit null.
+    in_some: bool,
+    /// Whether the value is wrapped in a `Some(Some(_))`, an empty string
+    /// inside it is sent whatever the policy.
+    in_nested_some: bool,
+    /// Whether the value is wrapped in a [`Raw`](crate::Raw).
+    raw: bool,
 }
 
 impl<'a, W> Serializer<'a, W>
 where
     W: io::Write,
 {
-    pub fn new_from_toplevel(is_key: bool, first_param: &'a mut bool, key: &'a str, writer: &'a mut W) -> Self {
+    pub fn new_from_toplevel(
+        is_key: bool,
+        first_param: &'a mut bool,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
         Serializer {
             is_key,
             first_param,
             key,
             writer,
-            sequence_allowed: true,
+            config,
+            in_param: false,
+            seq_depth: 0,
+            depth: 0,
+            in_some: false,
+            in_nested_some: false,
+            raw: false,
         }
     }
 
-    pub fn new_from_seq(first_param: &'a mut bool, key: &'a str, writer: &'a mut W) -> Self {
+    /// The value of a map, nested in `depth` other maps.
+    pub fn new_from_map(
+        depth: usize,
+        first_param: &'a mut bool,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
+        Serializer {
+            is_key: true,
+            first_param,
+            key,
+            writer,
+            config,
+            in_param: false,
+            seq_depth: 0,
+            depth,
+            in_some: false,
+            in_nested_some: false,
+            raw: false,
+        }
+    }
+
+    /// An element of a sequence written in the parameter the sequence already
+    /// opened.
+    pub fn new_from_seq(
+        seq_depth: usize,
+        first_param: &'a mut bool,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
         Serializer {
             is_key: false,
             first_param,
             key,
             writer,
-            sequence_allowed: false,
+            config,
+            in_param: true,
+            seq_depth,
+            depth: 0,
+            in_some: false,
+            in_nested_some: false,
+            raw: false,
         }
     }
+
+    /// An element of a sequence written in its own parameter.
+    pub fn new_from_seq_param(
+        seq_depth: usize,
+        first_param: &'a mut bool,
+        key: &'a str,
+        writer: &'a mut W,
+        config: &'a Config,
+    ) -> Self {
+        Serializer {
+            is_key: true,
+            first_param,
+            key,
+            writer,
+            config,
+            in_param: false,
+            seq_depth,
+            depth: 0,
+            in_some: false,
+            in_nested_some: false,
+            raw: false,
+        }
+    }
+
+    fn write_value(self, v: impl Display) -> Result<(), crate::Error> {
+        if !self.in_param {
+            start_param(
+                self.writer,
+                self.first_param,
+                self.is_key,
+                self.key,
+                self.config,
+            )?;
+        }
+        if self.is_element() {
+            let v = v.to_string();
+            write!(
+                self.writer,
+                "{}",
+                self.config.escape_element(&v, self.seq_depth)?
+            )?;
+        } else {
+            write!(self.writer, "{v}")?;
+        }
+        Ok(())
+    }
+
+    /// Whether the value is an element sharing its parameter with the other
+    /// elements of a sequence.
+    fn is_element(&self) -> bool {
+        self.in_param && self.seq_depth != 0
+    }
+
+    /// Whether a sequence can be written in place of the value.
+    fn sequence_allowed(&self) -> bool {
+        match self.seq_depth {
+            // an opened parameter can only be shared with the delimited styles
+            0 => self.config.delimiter(0).is_some() || !self.in_param,
+            depth => self.config.delimiter(depth).is_some(),
+        }
+    }
+
+    /// Open the parameter with the `Variant:` prefix, or the `index:` prefix
+    /// with [`EnumStyle::Index`], the value is then written right after it.
+    fn start_variant(mut self, variant: &str) -> Result<Self, crate::Error> {
+        let prefix = format!("{}:", self.config.encoding.encode(variant));
+        if self.is_element() {
+            let prefix = self.config.escape_element(&prefix, self.seq_depth)?;
+            write!(self.writer, "{prefix}")?;
+        } else {
+            if !self.in_param {
+                start_param(
+                    self.writer,
+                    self.first_param,
+                    self.is_key,
+                    self.key,
+                    self.config,
+                )?;
+            }
+            write!(self.writer, "{prefix}")?;
+        }
+        self.in_param = true;
+        Ok(self)
+    }
+
+    /// The parameter holding the variant of the value with
+    /// [`EnumStyle::TagParam`].
+    fn variant_tag(&self, suffix: &str, variant: &str) -> Result<(String, String), crate::Error> {
+        if self.seq_depth != 0 || self.in_param {
+            return Err(crate::Error::UnsupportedNestedStruct("tagged variant"));
+        }
+        let key = format!("{}{}", self.key, self.config.encoding.encode(suffix));
+        Ok((key, self.config.encoding.encode(variant)))
+    }
+
+    /// Write what the policy asks for in place of a value that has nothing to
+    /// show.
+    pub fn serialize_omitted(self, policy: OmitPolicy) -> Result<(), crate::Error> {
+        match policy {
+            // an element in its own parameter must keep its position
+            OmitPolicy::Omit if self.seq_depth != 0 && !self.in_param => self.write_value(""),
+            OmitPolicy::Omit => Ok(()),
+            OmitPolicy::Empty => self.write_value(""),
+            OmitPolicy::Null => self.serialize_null(),
+        }
+    }
+
+    /// The policy of an empty string, a `Some(Some(""))` isn't omitted.
+    fn empty_str_policy(&self) -> OmitPolicy {
+        if self.in_nested_some {
+            OmitPolicy::Empty
+        } else {
+            self.config.empty_str_policy
+        }
+    }
+
+    fn serialize_null(self) -> Result<(), crate::Error> {
+        let token = self.config.encoding.encode(&self.config.null_token);
+        self.write_value(token)
+    }
+
+    /// Whether a struct or a map can be written in place of the value.
+    fn nesting_allowed(&self) -> bool {
+        // the elements of a sequence can't be nested values
+        self.seq_depth == 0 && self.config.nesting_style.allows(self.depth)
+    }
+
+    fn serialize_nested(self) -> super::map::Serializer<'a, W> {
+        super::map::Serializer::new_nested(
+            self.key,
+            self.depth + 1,
+            self.first_param,
+            self.writer,
+            self.config,
+        )
+    }
+}
+
+/// Render a value as an element of `seq_depth` sequences.
+pub fn render<T>(value: &T, seq_depth: usize, config: &Config) -> Result<String, crate::Error>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut writer = Vec::new();
+    let mut first_param = false;
+    value.serialize(Serializer::new_from_seq(
+        seq_depth,
+        &mut first_param,
+        "",
+        &mut writer,
+        config,
+    ))?;
+    Ok(String::from_utf8(writer)?)
+}
+
+/// Write a whole parameter, the `value` being already encoded.
+pub fn write_param<W>(
+    writer: &mut W,
+    first_param: &mut bool,
+    (key, value): (&str, &str),
+    config: &Config,
+) -> Result<(), crate::Error>
+where
+    W: io::Write,
+{
+    start_param(writer, first_param, true, key, config)?;
+    write!(writer, "{value}")?;
+    Ok(())
+}
+
+/// Write the separator and the key of a new parameter.
+pub fn start_param<W>(
+    writer: &mut W,
+    first_param: &mut bool,
+    is_key: bool,
+    key: &str,
+    config: &Config,
+) -> Result<(), crate::Error>
+where
+    W: io::Write,
+{
+    if !*first_param && is_key {
+        write!(writer, "{}", config.pair_separator)?;
+    } else {
+        *first_param = false;
+    }
+    write!(writer, "{key}{}", config.key_value_separator)?;
+    Ok(())
 }
 
 impl<'a, W> ::serde::ser::Serializer for Serializer<'a, W>
@@ -49,205 +302,100 @@ where
     type SerializeTupleStruct = super::seq::Serializer<'a, W>;
     type SerializeTupleVariant = super::seq::Serializer<'a, W>;
 
-    type SerializeMap = serde::ser::Impossible<Self::Ok, Self::Error>;
-    type SerializeStruct = serde::ser::Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = super::map::ValueSerializer<'a, W>;
+    type SerializeStruct = super::map::Serializer<'a, W>;
     type SerializeStructVariant = serde::ser::Impossible<Self::Ok, Self::Error>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
+        match self.config.bool_style.token(v) {
+            Some(token) => self.write_value(token),
+            None => self.serialize_omitted(OmitPolicy::Omit),
         }
-        Ok(())
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write_value(v)
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        self.write_value(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.write_value(v)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        let v = self.config.format_float(v)?;
+        self.write_value(v)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
-        }
-        Ok(())
+        let v = self.config.format_float(v)?;
+        self.write_value(v)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}={v}", self.key)?;
-        } else {
-            write!(self.writer, "{v}")?;
+        // yaup writes the characters as-is
+        if self.config.compat == Compat::Yaup {
+            return self.write_value(v);
         }
-        Ok(())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        let v = String::from_iter(form_urlencoded::byte_serialize(v.as_bytes()));
-        if !v.is_empty() {
-            if self.sequence_allowed {
-                if !*self.first_param && self.is_key {
-                    write!(self.writer, "&")?;
-                } else {
-                    *self.first_param = false;
-                }
-                write!(self.writer, "{}={v}", self.key)?;
-            } else {
-                write!(self.writer, "{v}")?;
-            }
+        if v.is_empty() {
+            let policy = self.empty_str_policy();
+            return self.serialize_omitted(policy);
         }
-        Ok(())
+        if self.raw {
+            crate::raw::check(v, self.config)?;
+            return self.write_value(v);
+        }
+        let v = self.config.encoding.encode(v);
+        self.write_value(v)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        if !self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
+        if let Some(encoded) = self.config.bytes_encoding.encode(v, self.config.encoding) {
+            if encoded.is_empty() {
+                let policy = self.empty_str_policy();
+                return self.serialize_omitted(policy);
             }
+            return self.write_value(encoded);
+        }
+        if !self.sequence_allowed() {
             return Err(Self::Error::UnsupportedNestedStruct("bytes"));
         }
-        let mut serializer = super::seq::Serializer::new(self.key, &mut *self.writer);
+        let mut serializer = self.serialize_seq(Some(v.len()))?;
         for v in v {
             serializer.serialize_element(v)?;
         }
@@ -255,108 +403,168 @@ where
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        // the `Some(None)` of an `Option<Option<T>>`
+        if self.in_some {
+            return self.serialize_null();
+        }
+        let policy = self.config.none_policy;
+        // only the prefix of the variant would be left
+        if policy == OmitPolicy::Omit && self.in_param && self.seq_depth == 0 {
+            return Err(crate::Error::UnsupportedNestedStruct(
+                "prefixed variant holding an omitted none",
+            ));
+        }
+        self.serialize_omitted(policy)
     }
 
-    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
+        self.in_nested_some = self.in_some;
+        self.in_some = true;
         value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        let policy = self.config.unit_policy;
+        self.serialize_omitted(policy)
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        Err(Self::Error::UnsupportedNestedStruct("unit struct"))
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        match self.config.unit_struct_style {
+            UnitStructStyle::Unit => self.serialize_unit(),
+            UnitStructStyle::Name => self.serialize_str(name),
+        }
     }
 
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        use serde::Serialize;
-        variant.serialize(self)
+        if self.config.enum_style == EnumStyle::Index {
+            return self.write_value(variant_index);
+        }
+        let variant = self.config.variant_case.apply(variant);
+        self.serialize_str(&variant)
     }
 
     fn serialize_newtype_struct<T>(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
+        self.raw = name == crate::raw::NAME;
         value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
-        value.serialize(self)
+        let variant = self.config.variant_case.apply(variant);
+        match self.config.enum_style {
+            EnumStyle::Name => value.serialize(self),
+            EnumStyle::Index => value.serialize(self.start_variant(&variant_index.to_string())?),
+            EnumStyle::Prefixed => value.serialize(self.start_variant(&variant)?),
+            EnumStyle::TagParam(suffix) => {
+                let tag = self.variant_tag(suffix, &variant)?;
+                let (writer, first_param, config) = (self.writer, self.first_param, self.config);
+                value.serialize(Serializer {
+                    writer: &mut *writer,
+                    first_param: &mut *first_param,
+                    ..self
+                })?;
+                write_param(writer, first_param, (&tag.0, &tag.1), config)
+            }
+        }
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        if self.sequence_allowed {
-            if !_len.is_some_and(|len| len == 0) || _len.is_none() {
-                if !*self.first_param && self.is_key {
-                    write!(self.writer, "&")?;
-                } else {
-                    *self.first_param = false;
-                }
-                write!(self.writer, "{}=", self.key)?;
-            }
-            Ok(super::seq::Serializer::new(&self.key, self.writer))
+        if self.sequence_allowed() {
+            Ok(super::seq::Serializer::new(
+                self.first_param,
+                self.is_key,
+                self.in_param,
+                self.seq_depth,
+                self.key,
+                self.writer,
+                self.config,
+            ))
         } else {
             Err(Self::Error::UnsupportedNestedStruct("sequence"))
         }
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        if self.sequence_allowed {
-            if !*self.first_param && self.is_key {
-                write!(self.writer, "&")?;
-            } else {
-                *self.first_param = false;
-            }
-            write!(self.writer, "{}=", self.key)?;
-            Ok(super::seq::Serializer::new(self.key, self.writer))
-        } else {
-            Err(Self::Error::UnsupportedNestedStruct("sequence"))
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        // the 2-tuples inside a sequence are written as inline pairs
+        if len == 2 && self.seq_depth != 0 && self.config.inline_pairs.is_some() {
+            return Ok(super::seq::Serializer::new_pair(
+                self.first_param,
+                self.is_key,
+                self.in_param,
+                self.seq_depth,
+                self.key,
+                self.writer,
+                self.config,
+            ));
         }
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        Err(Self::Error::UnsupportedNestedStruct("tuple struct"))
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        Err(Self::Error::UnsupportedNestedStruct("tuple variant"))
+        let variant = self.config.variant_case.apply(variant);
+        match self.config.enum_style {
+            EnumStyle::Name => self.serialize_seq(Some(len)),
+            EnumStyle::Index => self
+                .start_variant(&variant_index.to_string())?
+                .serialize_seq(Some(len)),
+            EnumStyle::Prefixed => self.start_variant(&variant)?.serialize_seq(Some(len)),
+            EnumStyle::TagParam(suffix) => {
+                let tag = self.variant_tag(suffix, &variant)?;
+                Ok(self.serialize_seq(Some(len))?.with_tag(tag))
+            }
+        }
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Err(Self::Error::UnsupportedNestedStruct("map"))
+        if self.config.inline_pairs.is_some() {
+            // a map written as inline pairs is a sequence of pairs
+            return match self.serialize_seq(None) {
+                Ok(seq) => Ok(super::map::ValueSerializer::Inline(seq)),
+                Err(_) => Err(Self::Error::UnsupportedNestedStruct("map")),
+            };
+        }
+        if self.nesting_allowed() {
+            Ok(super::map::ValueSerializer::Nested(self.serialize_nested()))
+        } else {
+            Err(Self::Error::UnsupportedNestedStruct("map"))
+        }
     }
 
     fn serialize_struct(
@@ -364,7 +572,11 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Err(Self::Error::UnsupportedNestedStruct("struct"))
+        if self.nesting_allowed() {
+            Ok(self.serialize_nested())
+        } else {
+            Err(Self::Error::UnsupportedNestedStruct("struct"))
+        }
     }
 
     fn serialize_struct_variant(